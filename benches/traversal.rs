@@ -0,0 +1,123 @@
+//!
+//! Traversal benchmark.
+//! Compares the mutable traversals against the previous recursive implementation,
+//! which cloned each node's children and built a Handle per child.
+//!
+//! Run with: cargo bench --bench traversal
+//!
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use mach_core::dag::*;
+
+
+/// Counts visited nodes.
+#[derive(Default)]
+struct CountVisitor {
+    count: usize,
+}
+impl Visitor for CountVisitor {
+    fn visit_mut(&mut self, node: &mut MachNode) {
+        self.count += black_box(node.children.len()) + 1;
+    }
+}
+
+
+/// Balanced tree with 'branching' children per node, 'depth' levels below root.
+fn build(branching: u32, depth: u32) -> MachGraph {
+    let mut graph = MachGraph::new("bench");
+    let mut level = vec![0u32];
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for parent in &level {
+            for _ in 0..branching {
+                let index = graph.nodes.len() as u32;
+                graph.push(MachNode::from((String::from("node"), *parent)));
+                graph.nodes[*parent as usize].children.push(index);
+                next.push(index);
+            }
+        }
+        level = next;
+    }
+    graph
+}
+
+
+/// Previous pre-visit: recursive, clones children and builds a Handle per child.
+fn legacy_pre_visit_mut(graph: &mut MachGraph, visitor: &mut impl Visitor, handle: &Handle) {
+    let mut children: Vec<u32> = Vec::new();
+    if let Some(node) = graph.get_node_mut(handle) {
+        node.accept_mut(visitor);
+        children = node.children.clone();
+    }
+    for child in &children {
+        legacy_pre_visit_mut(graph, visitor, &Handle::from(*child));
+    }
+}
+
+
+/// Previous post-visit: recursive, clones children and builds a Handle per child.
+fn legacy_post_visit_mut(graph: &mut MachGraph, visitor: &mut impl Visitor, handle: &Handle) {
+    let mut children: Vec<u32> = Vec::new();
+    if let Some(node) = graph.get_node_mut(handle) {
+        children = node.children.clone();
+    }
+    for child in &children {
+        legacy_post_visit_mut(graph, visitor, &Handle::from(*child));
+    }
+    if let Some(node) = graph.get_node_mut(handle) {
+        node.accept_mut(visitor);
+    }
+}
+
+
+/// Best time of a number of runs.
+fn time(runs: u32, mut f: impl FnMut() -> usize) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let start = Instant::now();
+        black_box(f());
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+
+fn report(name: &str, legacy: Duration, current: Duration) {
+    println!(
+        "{:<16} legacy {:>10.3?}  current {:>10.3?}  speedup {:.2}x",
+        name, legacy, current, legacy.as_secs_f64() / current.as_secs_f64()
+    );
+}
+
+
+fn main() {
+    for (branching, depth) in [(4, 9), (16, 4), (2, 18)] {
+        let mut graph = build(branching, depth);
+        let root = graph.root.clone();
+        println!("-- {} nodes (branching {}, depth {})", graph.nodes.len(), branching, depth);
+
+        let legacy = time(10, || {
+            let mut visitor = CountVisitor::default();
+            legacy_pre_visit_mut(&mut graph, &mut visitor, &root);
+            visitor.count
+        });
+        let current = time(10, || {
+            let mut visitor = CountVisitor::default();
+            graph.pre_visit_mut(&mut visitor).expect("acyclic");
+            visitor.count
+        });
+        report("pre_visit_mut", legacy, current);
+
+        let legacy = time(10, || {
+            let mut visitor = CountVisitor::default();
+            legacy_post_visit_mut(&mut graph, &mut visitor, &root);
+            visitor.count
+        });
+        let current = time(10, || {
+            let mut visitor = CountVisitor::default();
+            graph.post_visit_mut(&mut visitor).expect("acyclic");
+            visitor.count
+        });
+        report("post_visit_mut", legacy, current);
+    }
+}
\ No newline at end of file
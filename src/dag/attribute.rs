@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+
+///
+/// Attributes of a node, ordered by key.
+///
+pub type Attributes = BTreeMap<String, Attribute>;
+
+
+///
+/// Attribute.
+/// Small typed value stored on a node (labels, flags, source file, colours...).
+/// Serialized as the plain value, e.g. 'true', '3', '1.5', '"red"' or '[1, 2]'.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Attribute {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Attribute>),
+}
+
+
+///
+/// Implementation for Attribute.
+///
+impl Attribute {
+    /// Bool value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self { Attribute::Bool(value) => Some(*value), _ => None }
+    }
+
+
+    /// Int value.
+    pub fn as_int(&self) -> Option<i64> {
+        match self { Attribute::Int(value) => Some(*value), _ => None }
+    }
+
+
+    /// Float value (ints are converted).
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Attribute::Float(value) => Some(*value),
+            Attribute::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+
+    /// String value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self { Attribute::String(value) => Some(value), _ => None }
+    }
+
+
+    /// List value.
+    pub fn as_list(&self) -> Option<&[Attribute]> {
+        match self { Attribute::List(value) => Some(value), _ => None }
+    }
+
+
+    /// Does this value match the text of a path query ('[key=text]')?
+    /// Numbers compare by value, strings may be quoted and lists match if any item matches.
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Attribute::Bool(value) => text.parse::<bool>() == Ok(*value),
+            Attribute::Int(value) => text.parse::<i64>() == Ok(*value),
+            Attribute::Float(value) => text.parse::<f64>() == Ok(*value),
+            Attribute::String(value) => value == text.trim_matches(|c| c == '"' || c == '\''),
+            Attribute::List(items) => items.iter().any(|item| item.matches(text)),
+        }
+    }
+}
+
+
+///
+/// Display implementation (the value as written in path queries).
+///
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attribute::Bool(value) => write!(f, "{}", value),
+            Attribute::Int(value) => write!(f, "{}", value),
+            Attribute::Float(value) => write!(f, "{:?}", value),
+            Attribute::String(value) => write!(f, "{}", value),
+            Attribute::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            },
+        }
+    }
+}
+
+
+impl From<bool> for Attribute {
+    fn from(value: bool) -> Self { Attribute::Bool(value) }
+}
+
+impl From<i32> for Attribute {
+    fn from(value: i32) -> Self { Attribute::Int(value as i64) }
+}
+
+impl From<i64> for Attribute {
+    fn from(value: i64) -> Self { Attribute::Int(value) }
+}
+
+impl From<f32> for Attribute {
+    fn from(value: f32) -> Self { Attribute::Float(value as f64) }
+}
+
+impl From<f64> for Attribute {
+    fn from(value: f64) -> Self { Attribute::Float(value) }
+}
+
+impl From<&str> for Attribute {
+    fn from(value: &str) -> Self { Attribute::String(String::from(value)) }
+}
+
+impl From<String> for Attribute {
+    fn from(value: String) -> Self { Attribute::String(value) }
+}
+
+impl<T: Into<Attribute>> From<Vec<T>> for Attribute {
+    fn from(items: Vec<T>) -> Self { Attribute::List(items.into_iter().map(Into::into).collect()) }
+}
\ No newline at end of file
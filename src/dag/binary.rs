@@ -0,0 +1,976 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use super::{MachGraph, Handle, ComponentRef, Attribute, Attributes, Tags, Edge, Edges, Instance, GraphError};
+use super::format::{GraphIn, NodeIn, PrototypeIn};
+use super::prototype::Synced;
+use super::path::segments;
+use super::node::is_zero_sized;
+
+
+///
+/// Version of the binary format written by MachGraph::to_binary.
+/// Integers are LEB128 varints unless noted; u32 are little endian.
+///
+/// ```text
+/// header    magic "MACH", u32 version, u32 node count, u32 root, u32 string count,
+///           u32 offset of nodes, u32 offset of extras, u32 offset of the checksum
+/// strings   u32 offset of every block of 64 strings, then (length, utf-8 bytes) per string
+/// nodes     u32 offset of every block of 64 nodes, then per node: name string + 1 (0 for a removed slot),
+///           zigzag(parent - index) (0 for no parent), component count, (kind, index) per component
+/// extras    graph name, graph index, child lists (node, child count, zigzag(child - node) per child), attributes,
+///           payloads (JSON), tags, edges, prototypes (nested files), instances
+/// checksum  u32 FNV-1a of everything before it
+/// ```
+///
+/// Children are derived from parents: the children of a node are the nodes naming it as their parent, in index order.
+/// Only nodes whose children can't be derived (shared children, or siblings out of index order) have their child list
+/// in the extras, much like to_json only writes a parent that isn't the first node listing it.
+/// Names and other strings are written once in the string table and referenced by number.
+/// Attribute lists can be nested at most MAX_DEPTH deep.
+///
+/// Node payloads are written as JSON (unless zero sized). This is a deliberate limitation: payloads are any
+/// Serialize type, as for to_json, and a compact encoding would need a schema from every payload type.
+/// It costs size and a JSON parse per payload when decoding; BinaryGraph doesn't read payloads at all.
+///
+pub const BINARY_VERSION: u32 = 1;
+
+/// First bytes of every binary file.
+const MAGIC: &[u8; 4] = b"MACH";
+
+/// Size of the fixed header.
+const HEADER: usize = 32;
+
+/// Deepest nesting of attribute lists, like serde_json's recursion limit.
+pub const MAX_DEPTH: usize = 128;
+
+/// Records per block. The offset of every block is stored, so a record is found by decoding at most BLOCK - 1 others.
+const BLOCK: u32 = 64;
+
+
+/// Writes a graph, collecting its strings in a table.
+struct Writer<'a> {
+    /// String table, in order of first use.
+    strings: Vec<&'a str>,
+
+    /// Number of each string in the table.
+    numbers: HashMap<&'a str, u32>,
+}
+
+
+///
+/// Implementation for Writer.
+///
+impl<'a> Writer<'a> {
+    /// Write a graph.
+    fn write<T: Serialize>(graph: &'a MachGraph<T>) -> Result<Vec<u8>, GraphError> {
+        let mut writer = Writer { strings: Vec::new(), numbers: HashMap::new() };
+        let parents: Vec<_> = graph.nodes.iter().map(|node| (!node.removed && node.has_parent()).then_some(node.parent)).collect();
+        let derived = derive(&parents, |parent| graph.nodes.get(parent as usize).is_some_and(|node| !node.removed))?;
+
+        let mut node_blocks = Vec::new();
+        let mut nodes = Vec::new();
+        for (index, node) in graph.nodes.iter().enumerate() {
+            if (index as u32).is_multiple_of(BLOCK) { node_blocks.push(nodes.len()); }
+            if node.removed {
+                put(&mut nodes, 0);
+                continue;
+            }
+            let name = writer.string(&node.name);
+            put(&mut nodes, name as u64 + 1);
+            put(&mut nodes, relative(node.index, parents[index]));
+            writer.components(&mut nodes, &node.components);
+        }
+
+        let mut extras = Vec::new();
+        writer.extras(&mut extras, graph, &derived)?;
+
+        let mut string_blocks = Vec::new();
+        let mut strings = Vec::new();
+        for (number, string) in writer.strings.iter().enumerate() {
+            if (number as u32).is_multiple_of(BLOCK) { string_blocks.push(strings.len()); }
+            put(&mut strings, string.len() as u64);
+            strings.extend_from_slice(string.as_bytes());
+        }
+
+        let strings_at = HEADER;
+        let nodes_at = strings_at + string_blocks.len() * 4 + strings.len();
+        let extras_at = nodes_at + node_blocks.len() * 4 + nodes.len();
+        let checksum_at = extras_at + extras.len();
+
+        let mut bytes = Vec::with_capacity(checksum_at + 4);
+        bytes.extend_from_slice(MAGIC);
+        for value in [BINARY_VERSION, graph.nodes.len() as u32, graph.root_index(), writer.strings.len() as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [nodes_at, extras_at, checksum_at] {
+            bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        let records_at = strings_at + string_blocks.len() * 4;
+        for block in string_blocks {
+            bytes.extend_from_slice(&((records_at + block) as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&strings);
+        let records_at = nodes_at + node_blocks.len() * 4;
+        for block in node_blocks {
+            bytes.extend_from_slice(&((records_at + block) as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&nodes);
+        bytes.extend_from_slice(&extras);
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        Ok(bytes)
+    }
+
+
+    /// Number of a string, adding it to the table.
+    fn string(&mut self, string: &'a str) -> u32 {
+        if let Some(number) = self.numbers.get(string) { return *number; }
+        let number = self.strings.len() as u32;
+        self.strings.push(string);
+        self.numbers.insert(string, number);
+        number
+    }
+
+
+    /// Write everything but the node records, given the children derived from parents.
+    fn extras<T: Serialize>(&mut self, out: &mut Vec<u8>, graph: &'a MachGraph<T>, derived: &[Vec<u32>]) -> Result<(), GraphError> {
+        let name = self.string(&graph.name);
+        put(out, name as u64);
+        put(out, graph.index as u64);
+
+        let listed: Vec<_> = graph.nodes.iter().filter(|node| !node.removed && node.children != derived[node.index as usize]).collect();
+        put(out, listed.len() as u64);
+        for node in listed {
+            put(out, node.index as u64);
+            put(out, node.children.len() as u64);
+            for child in &node.children {
+                put(out, relative(node.index, Some(*child)));
+            }
+        }
+
+        let attributed: Vec<_> = graph.nodes.iter().filter(|node| !node.removed && node.attributes.is_some()).collect();
+        put(out, attributed.len() as u64);
+        for node in attributed {
+            put(out, node.index as u64);
+            self.attributes(out, node.attributes.as_deref())?;
+        }
+
+        let weighted: Vec<_> = graph.nodes.iter().filter(|node| !node.removed && !is_zero_sized(&node.data)).collect();
+        put(out, weighted.len() as u64);
+        for node in weighted {
+            let data = serde_json::to_vec(&node.data).map_err(GraphError::format)?;
+            put(out, node.index as u64);
+            put(out, data.len() as u64);
+            out.extend_from_slice(&data);
+        }
+
+        let tags: Vec<&str> = graph.tags.names().collect();
+        put(out, tags.len() as u64);
+        for tag in tags {
+            let number = self.string(tag);
+            put(out, number as u64);
+            put(out, graph.tags.count(tag) as u64);
+            let mut last = 0;
+            for index in graph.tags.nodes(tag) {
+                put(out, (index - last) as u64);
+                last = index;
+            }
+        }
+
+        put(out, graph.edges.len() as u64);
+        for edge in graph.edges.iter() {
+            put(out, edge.from as u64);
+            put(out, edge.to as u64);
+            let label = self.string(&edge.label);
+            put(out, label as u64);
+            match &edge.data {
+                Some(data) => {
+                    out.push(1);
+                    self.attribute(out, data, 0)?;
+                },
+                None => out.push(0),
+            }
+        }
+
+        put(out, graph.prototypes.len() as u64);
+        for prototype in &graph.prototypes {
+            let file = Writer::write(&prototype.graph)?;
+            put(out, file.len() as u64);
+            out.extend_from_slice(&file);
+            put(out, prototype.synced.len() as u64);
+            for synced in &prototype.synced {
+                let Some(synced) = synced else {
+                    out.push(0);
+                    continue;
+                };
+                out.push(1);
+                let name = self.string(&synced.name);
+                put(out, name as u64);
+                self.components(out, &synced.components);
+                self.attributes(out, synced.attributes.as_deref())?;
+            }
+        }
+
+        put(out, graph.instances.len() as u64);
+        for instance in &graph.instances {
+            put(out, instance.prototype as u64);
+            put(out, instance.root as u64);
+            put(out, instance.nodes.len() as u64);
+            for index in &instance.nodes {
+                put(out, if *index == u32::MAX { 0 } else { *index as u64 + 1 });
+            }
+        }
+        Ok(())
+    }
+
+
+    /// Write components.
+    fn components(&mut self, out: &mut Vec<u8>, components: &[ComponentRef]) {
+        put(out, components.len() as u64);
+        for component in components {
+            put(out, component.kind as u64);
+            put(out, component.index as u64);
+        }
+    }
+
+
+    /// Write attributes.
+    fn attributes(&mut self, out: &mut Vec<u8>, attributes: Option<&'a Attributes>) -> Result<(), GraphError> {
+        let Some(attributes) = attributes else {
+            put(out, 0);
+            return Ok(());
+        };
+        put(out, attributes.len() as u64);
+        for (key, value) in attributes {
+            let key = self.string(key);
+            put(out, key as u64);
+            self.attribute(out, value, 0)?;
+        }
+        Ok(())
+    }
+
+
+    /// Write an attribute: a kind byte, then its value. 'depth' is the number of lists it is in.
+    fn attribute(&mut self, out: &mut Vec<u8>, attribute: &'a Attribute, depth: usize) -> Result<(), GraphError> {
+        match attribute {
+            Attribute::Bool(value) => out.extend_from_slice(&[0, *value as u8]),
+            Attribute::Int(value) => {
+                out.push(1);
+                put(out, zigzag(*value));
+            },
+            Attribute::Float(value) => {
+                out.push(2);
+                out.extend_from_slice(&value.to_le_bytes());
+            },
+            Attribute::String(value) => {
+                out.push(3);
+                let value = self.string(value);
+                put(out, value as u64);
+            },
+            Attribute::List(values) => {
+                if depth == MAX_DEPTH { return Err(GraphError::format(format!("attribute lists nested deeper than {}", MAX_DEPTH))); }
+                out.push(4);
+                put(out, values.len() as u64);
+                for value in values {
+                    self.attribute(out, value, depth + 1)?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+
+/// Children derived from parents: the nodes with a parent, in index order, are children of it.
+/// 'parents' has the parent of every node that has one (None for the rest and removed slots).
+fn derive(parents: &[Option<u32>], live: impl Fn(u32) -> bool) -> Result<Vec<Vec<u32>>, GraphError> {
+    let mut children = vec![Vec::new(); parents.len()];
+    for (index, parent) in parents.iter().enumerate() {
+        let Some(parent) = *parent else { continue; };
+        if !live(parent) { return Err(GraphError::format(format!("node {} has missing parent {}", index, parent))); }
+        children[parent as usize].push(index as u32);
+    }
+    Ok(children)
+}
+
+
+/// Another node relative to a node: zigzag(other - index), 0 for none.
+fn relative(index: u32, other: Option<u32>) -> u64 {
+    other.map_or(0, |other| zigzag(other as i64 - index as i64))
+}
+
+
+/// Write a varint.
+fn put(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+
+/// Signed to unsigned, keeping small values small.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+
+/// Unsigned back to signed.
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+
+/// FNV-1a hash of the file.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+
+///
+/// Cursor.
+/// Reads values from a byte slice, failing (None) instead of reading past its end.
+///
+#[derive(Debug, Clone, Copy)]
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+
+///
+/// Implementation for Cursor.
+///
+impl<'a> Cursor<'a> {
+    /// Read a varint.
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.at)?;
+            self.at += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 { return Some(value); }
+        }
+        None
+    }
+
+
+    /// Read a varint that must fit in a u32.
+    fn u32(&mut self) -> Option<u32> {
+        self.varint().and_then(|value| u32::try_from(value).ok())
+    }
+
+
+    /// Read bytes.
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len;
+        Some(bytes)
+    }
+
+
+    /// Read a byte.
+    fn byte(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+
+    /// Read a node relative to a node (see relative): None if bad or not below 'len', Some(None) for none.
+    fn relative(&mut self, index: u32, len: u32) -> Option<Option<u32>> {
+        match self.varint()? {
+            0 => Some(None),
+            value => u32::try_from(index as i64 + unzigzag(value)).ok().filter(|other| *other < len).map(Some),
+        }
+    }
+
+
+    /// Read a child list from the extras: the node and its children.
+    fn child_list(&mut self, len: u32) -> Option<(u32, Vec<u32>)> {
+        let node = self.u32()?;
+        let mut children = Vec::new();
+        for _ in 0..self.varint()? {
+            children.push(self.relative(node, len)??);
+        }
+        Some((node, children))
+    }
+
+
+    /// Skip a list of items of 'fields' varints each.
+    fn skip_list(&mut self, fields: usize) -> Option<()> {
+        for _ in 0..self.varint()? {
+            for _ in 0..fields { self.varint()?; }
+        }
+        Some(())
+    }
+
+
+    /// Skip a node record.
+    fn skip_node(&mut self) -> Option<()> {
+        if self.varint()? == 0 { return Some(()); }
+        self.varint()?;
+        self.skip_list(2)
+    }
+}
+
+
+/// Error for a bad node record.
+fn bad_record(index: u32) -> GraphError {
+    GraphError::format(format!("bad record of node {}", index))
+}
+
+
+/// First node that matches.
+fn first<'a>(nodes: impl Iterator<Item = Result<BinaryNode<'a>, GraphError>>, matches: impl Fn(&BinaryNode<'a>) -> Result<bool, GraphError>) -> Result<Option<BinaryNode<'a>>, GraphError> {
+    for node in nodes {
+        let node = node?;
+        if matches(&node)? { return Ok(Some(node)); }
+    }
+    Ok(None)
+}
+
+
+/// Little endian u32 at an offset.
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+
+///
+/// BinaryGraph.
+/// Zero-copy reader over a binary graph file (see MachGraph::to_binary), e.g. a memory-mapped one.
+/// Nodes and paths are looked up directly in the bytes; nothing is decoded up front.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryGraph<'a> {
+    bytes: &'a [u8],
+    nodes: u32,
+    root: u32,
+    strings: u32,
+    nodes_at: usize,
+    extras_at: usize,
+}
+
+
+///
+/// BinaryNode.
+/// A node read from a BinaryGraph, whose record has been checked. Its name borrows the file.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryNode<'a> {
+    /// Index of this node within graph.
+    pub index: u32,
+
+    /// Name of this node.
+    pub name: &'a str,
+
+    /// Parent index within graph. Same as 'index' means no parent.
+    pub parent: u32,
+
+    /// Encoded components.
+    components: Cursor<'a>,
+}
+
+
+///
+/// Implementation for BinaryNode.
+///
+impl<'a> BinaryNode<'a> {
+    /// Has a parent?
+    pub fn has_parent(&self) -> bool {
+        self.parent != self.index
+    }
+
+
+    /// Components of this node.
+    pub fn components(&self) -> impl Iterator<Item = ComponentRef> + 'a {
+        // read_node has checked the list, so this reads all of it.
+        let mut cursor = self.components;
+        let count = cursor.varint().unwrap_or(0);
+        (0..count).map_while(move |_| Some(ComponentRef::new(cursor.u32()?, cursor.u32()?)))
+    }
+}
+
+
+///
+/// Implementation for BinaryGraph.
+///
+impl<'a> BinaryGraph<'a> {
+    /// Open a binary graph file, checking its header, version and checksum.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, GraphError> {
+        let graph = Self::new_unchecked(bytes)?;
+        graph.verify()?;
+        Ok(graph)
+    }
+
+
+    /// Open a binary graph file without checking its checksum, which reads the whole file.
+    /// For large mapped files that only a few nodes are looked up in; call verify to check later.
+    pub fn new_unchecked(bytes: &'a [u8]) -> Result<Self, GraphError> {
+        if bytes.len() < HEADER + 4 || &bytes[..4] != MAGIC { return Err(GraphError::format("not a binary graph file")); }
+        let header = |at: usize| u32_at(bytes, at).unwrap_or_default();
+        let version = header(4);
+        if version > BINARY_VERSION { return Err(GraphError::UnsupportedVersion(version)); }
+        if version != BINARY_VERSION { return Err(GraphError::format(format!("bad binary version {}", version))); }
+
+        let (nodes_at, extras_at, checksum_at) = (header(20) as usize, header(24) as usize, header(28) as usize);
+        if checksum_at.checked_add(4) != Some(bytes.len()) { return Err(GraphError::format("truncated binary graph file")); }
+
+        let graph = Self { bytes, nodes: header(8), root: header(12), strings: header(16), nodes_at, extras_at };
+        let blocks = |count: u32| count.div_ceil(BLOCK) as usize * 4;
+        if HEADER + blocks(graph.strings) > nodes_at || nodes_at + blocks(graph.nodes) > extras_at || extras_at > checksum_at {
+            return Err(GraphError::format("bad section offsets"));
+        }
+        Ok(graph)
+    }
+
+
+    /// Check the file's checksum (new already has, to_graph always does).
+    pub fn verify(&self) -> Result<(), GraphError> {
+        let at = self.bytes.len() - 4;
+        if u32_at(self.bytes, at) != Some(checksum(&self.bytes[..at])) { return Err(GraphError::format("checksum mismatch")); }
+        Ok(())
+    }
+
+
+    /// Number of node slots (removed ones included).
+    pub fn len(&self) -> usize {
+        self.nodes as usize
+    }
+
+
+    /// No nodes at all?
+    pub fn is_empty(&self) -> bool {
+        self.nodes == 0
+    }
+
+
+    /// Root index.
+    pub fn root(&self) -> u32 {
+        self.root
+    }
+
+
+    /// Name of the graph.
+    pub fn name(&self) -> Result<&'a str, GraphError> {
+        let mut cursor = Cursor { bytes: self.bytes, at: self.extras_at };
+        self.string(cursor.u32().ok_or_else(|| GraphError::format("bad extras"))?)
+    }
+
+
+    /// String from the string table.
+    pub fn string(&self, number: u32) -> Result<&'a str, GraphError> {
+        self.read_string(number).ok_or_else(|| GraphError::format(format!("bad string {}", number)))
+    }
+
+
+    /// Get a node by index (None if out of range or removed, an error if its record is bad).
+    pub fn node(&self, index: u32) -> Result<Option<BinaryNode<'a>>, GraphError> {
+        if index >= self.nodes { return Ok(None); }
+        let mut cursor = self.block(self.nodes_at, index).ok_or_else(|| bad_record(index))?;
+        for _ in 0..index % BLOCK {
+            cursor.skip_node().ok_or_else(|| bad_record(index))?;
+        }
+        self.read_node(&mut cursor, index)
+    }
+
+
+    /// Get a node by handle (index if it has one, else path).
+    pub fn get_node(&self, handle: &Handle) -> Result<Option<BinaryNode<'a>>, GraphError> {
+        match handle.index {
+            Some(index) => self.node(index),
+            None => match self.index(&handle.path)? {
+                Some(index) => self.node(index),
+                None => Ok(None),
+            },
+        }
+    }
+
+
+    /// Live nodes in index order. A bad record is an error, after which iteration stops.
+    pub fn nodes(&self) -> impl Iterator<Item = Result<BinaryNode<'a>, GraphError>> + 'a {
+        let graph = *self;
+        let mut cursor = Cursor { bytes: self.bytes, at: self.nodes_at + self.nodes.div_ceil(BLOCK) as usize * 4 };
+        let mut failed = false;
+        (0..self.nodes).map_while(move |index| {
+            if failed { return None; }
+            let node = graph.read_node(&mut cursor, index);
+            failed = node.is_err();
+            Some(node.transpose())
+        }).flatten()
+    }
+
+
+    /// Children of a node, in order (none if it is out of range or removed).
+    /// Children aren't stored with nodes (see BINARY_VERSION), so unless the node's child list is in the extras
+    /// they are found by reading every node record.
+    pub fn children(&self, index: u32) -> Result<Vec<BinaryNode<'a>>, GraphError> {
+        if self.node(index)?.is_none() { return Ok(Vec::new()); }
+        let Some(children) = self.child_list(index)? else {
+            // Only the records of children are read in full.
+            let mut cursor = Cursor { bytes: self.bytes, at: self.nodes_at + self.nodes.div_ceil(BLOCK) as usize * 4 };
+            let mut children = Vec::new();
+            for child in 0..self.nodes {
+                let (mut record, mut peek) = (cursor, cursor);
+                cursor.skip_node().ok_or_else(|| bad_record(child))?;
+                if peek.varint() != Some(0) && peek.relative(child, self.nodes) == Some(Some(index)) {
+                    children.extend(self.read_node(&mut record, child)?);
+                }
+            }
+            return Ok(children);
+        };
+        children.into_iter()
+            .map(|child| self.node(child)?.ok_or_else(|| GraphError::format(format!("node {} has missing child {}", index, child))))
+            .collect()
+    }
+
+
+    /// Path of a node (names from the root, dot separated). None if it is out of range, removed or in a parent cycle.
+    pub fn path(&self, index: u32) -> Result<Option<String>, GraphError> {
+        let Some(mut node) = self.node(index)? else { return Ok(None); };
+        let mut names = vec![node.name];
+        for _ in 0..self.nodes {
+            if !node.has_parent() { break; }
+            let parent = node.parent;
+            node = self.node(parent)?.ok_or_else(|| GraphError::format(format!("node {} has missing parent {}", node.index, parent)))?;
+            names.push(node.name);
+        }
+        if node.has_parent() { return Ok(None); }
+        names.reverse();
+        Ok(Some(names.join(".")))
+    }
+
+
+    /// Attributes of a node (None if it has none).
+    pub fn attributes(&self, index: u32) -> Result<Option<Attributes>, GraphError> {
+        let mut extras = Extras { graph: self, cursor: Cursor { bytes: self.bytes, at: self.extras_at } };
+        let attributes = extras.attributes_of(index).ok_or_else(|| GraphError::format("bad extras"))?;
+        Ok(attributes.map(|attributes| *attributes))
+    }
+
+
+    /// Index of a path, found like Handle::index: the first segment is the first matching node,
+    /// following segments are children. Segments can be '*' and have attribute predicates.
+    pub fn index(&self, path: &str) -> Result<Option<u32>, GraphError> {
+        let mut current: Option<BinaryNode<'a>> = None;
+        for segment in segments(path) {
+            // Attributes are only decoded for predicates.
+            let matches = |node: &BinaryNode<'a>| match segment.name {
+                Some(name) if name != node.name => Ok(false),
+                _ if segment.predicates.is_empty() => Ok(true),
+                _ => Ok(segment.matches_with(node.name, self.attributes(node.index)?.as_ref())),
+            };
+            current = match current {
+                None => first(self.nodes(), matches)?,
+                Some(node) if segment.is_name() && matches(&node)? => Some(node),
+                Some(node) => first(self.children(node.index)?.into_iter().map(Ok), matches)?,
+            };
+            if current.is_none() { return Ok(None); }
+        }
+        Ok(current.map(|node| node.index))
+    }
+
+
+    /// Decode the whole graph, after checking the checksum.
+    pub fn to_graph<T: DeserializeOwned + Default>(&self) -> Result<MachGraph<T>, GraphError> {
+        self.verify()?;
+        self.file()?.into_graph()
+    }
+
+
+    /// Cursor at the block holding a record.
+    fn block(&self, section: usize, number: u32) -> Option<Cursor<'a>> {
+        let at = u32_at(self.bytes, section + (number / BLOCK) as usize * 4)?;
+        Some(Cursor { bytes: self.bytes, at: at as usize })
+    }
+
+
+    /// Child list of a node from the extras, if it has one.
+    fn child_list(&self, index: u32) -> Result<Option<Vec<u32>>, GraphError> {
+        let bad = || GraphError::format("bad extras");
+        let mut cursor = Cursor { bytes: self.bytes, at: self.extras_at };
+        // Graph name and index.
+        cursor.u32().ok_or_else(bad)?;
+        cursor.u32().ok_or_else(bad)?;
+        for _ in 0..cursor.varint().ok_or_else(bad)? {
+            let (node, children) = cursor.child_list(self.nodes).ok_or_else(bad)?;
+            if node == index { return Ok(Some(children)); }
+        }
+        Ok(None)
+    }
+
+
+    /// String from the string table, if it is there and valid.
+    fn read_string(&self, number: u32) -> Option<&'a str> {
+        if number >= self.strings { return None; }
+        let mut cursor = self.block(HEADER, number)?;
+        for _ in 0..number % BLOCK {
+            let len = cursor.varint()? as usize;
+            cursor.bytes(len)?;
+        }
+        let len = cursor.varint()? as usize;
+        std::str::from_utf8(cursor.bytes(len)?).ok()
+    }
+
+
+    /// Read the node record at a cursor (None for a removed slot).
+    fn read_node(&self, cursor: &mut Cursor<'a>, index: u32) -> Result<Option<BinaryNode<'a>>, GraphError> {
+        self.decode_node(cursor, index).ok_or_else(|| bad_record(index))
+    }
+
+
+    /// Decode and check a node record: None when it is bad, Some(None) for a removed slot.
+    fn decode_node(&self, cursor: &mut Cursor<'a>, index: u32) -> Option<Option<BinaryNode<'a>>> {
+        let name = cursor.u32()?;
+        if name == 0 { return Some(None); }
+        let parent = cursor.relative(index, self.nodes)?.unwrap_or(index);
+        let components = *cursor;
+        for _ in 0..cursor.varint()? {
+            cursor.u32()?;
+            cursor.u32()?;
+        }
+        let name = self.read_string(name - 1)?;
+        Some(Some(BinaryNode { index, name, parent, components }))
+    }
+
+
+    /// Decode the whole file, before checking it.
+    fn file<T: DeserializeOwned + Default>(&self) -> Result<GraphIn<T>, GraphError> {
+        let mut records = Vec::with_capacity(self.len());
+        let mut cursor = Cursor { bytes: self.bytes, at: self.nodes_at + self.nodes.div_ceil(BLOCK) as usize * 4 };
+        for index in 0..self.nodes {
+            records.push(self.read_node(&mut cursor, index)?);
+        }
+
+        // Children are derived from parents; the extras replace the lists that differ.
+        let parents: Vec<_> = records.iter().map(|node| node.filter(BinaryNode::has_parent).map(|node| node.parent)).collect();
+        let derived = derive(&parents, |parent| records.get(parent as usize).is_some_and(Option::is_some))?;
+        let nodes = records.iter().zip(derived).map(|(node, children)| node.map(|node| NodeIn {
+            name: String::from(node.name),
+            parent: Some(node.parent),
+            children,
+            components: node.components().collect(),
+            attributes: None,
+            data: T::default(),
+        })).collect();
+
+        let mut extras = Extras { graph: self, cursor: Cursor { bytes: self.bytes, at: self.extras_at } };
+        extras.read(nodes, self.root).ok_or_else(|| GraphError::format("bad extras"))?
+    }
+}
+
+
+///
+/// Reader of the extras section.
+///
+struct Extras<'g, 'a> {
+    graph: &'g BinaryGraph<'a>,
+    cursor: Cursor<'a>,
+}
+
+
+///
+/// Implementation for Extras.
+///
+impl Extras<'_, '_> {
+    /// Read everything but the node records.
+    /// Returns None when the section is bad, or the error of a bad payload or prototype file.
+    fn read<T: DeserializeOwned + Default>(&mut self, mut nodes: Vec<Option<NodeIn<T>>>, root: u32) -> Option<Result<GraphIn<T>, GraphError>> {
+        let name = self.string()?;
+        let index = self.cursor.u32()?;
+
+        for _ in 0..self.cursor.varint()? {
+            let (node, children) = self.cursor.child_list(self.graph.nodes)?;
+            nodes.get_mut(node as usize)?.as_mut()?.children = children;
+        }
+
+        for _ in 0..self.cursor.varint()? {
+            let node = self.cursor.u32()?;
+            let attributes = self.attributes()?;
+            nodes.get_mut(node as usize)?.as_mut()?.attributes = attributes;
+        }
+
+        for _ in 0..self.cursor.varint()? {
+            let node = self.cursor.u32()?;
+            let len = self.cursor.varint()? as usize;
+            let data = match serde_json::from_slice(self.cursor.bytes(len)?) {
+                Ok(data) => data,
+                Err(error) => return Some(Err(GraphError::format(format!("payload of node {}: {}", node, error)))),
+            };
+            nodes.get_mut(node as usize)?.as_mut()?.data = data;
+        }
+
+        let mut tags = Tags::default();
+        for _ in 0..self.cursor.varint()? {
+            let tag = self.string()?;
+            let mut index = 0u32;
+            for _ in 0..self.cursor.varint()? {
+                index = index.checked_add(self.cursor.u32()?)?;
+                tags.insert(index, &tag);
+            }
+        }
+
+        let mut edges = Edges::default();
+        for _ in 0..self.cursor.varint()? {
+            let (from, to, label) = (self.cursor.u32()?, self.cursor.u32()?, self.string()?);
+            let data = match self.cursor.byte()? {
+                0 => None,
+                _ => Some(self.attribute(0)?),
+            };
+            edges.insert(Edge { from, to, label, data });
+        }
+
+        let mut prototypes = Vec::new();
+        for _ in 0..self.cursor.varint()? {
+            let len = self.cursor.varint()? as usize;
+            let file = BinaryGraph::new_unchecked(self.cursor.bytes(len)?).and_then(|graph| graph.file::<()>());
+            let graph = match file {
+                Ok(graph) => graph,
+                Err(error) => return Some(Err(error)),
+            };
+            let mut synced = Vec::new();
+            for _ in 0..self.cursor.varint()? {
+                synced.push(match self.cursor.byte()? {
+                    0 => None,
+                    _ => Some(Synced { name: self.string()?, components: self.components()?, attributes: self.attributes()? }),
+                });
+            }
+            prototypes.push(PrototypeIn { graph, synced });
+        }
+
+        let mut instances = Vec::new();
+        for _ in 0..self.cursor.varint()? {
+            let (prototype, root) = (self.cursor.u32()?, self.cursor.u32()?);
+            let mut nodes = Vec::new();
+            for _ in 0..self.cursor.varint()? {
+                nodes.push(self.cursor.u32()?.checked_sub(1).unwrap_or(u32::MAX));
+            }
+            instances.push(Instance { prototype, root, nodes });
+        }
+
+        Some(Ok(GraphIn { name, index, root, nodes, tags, edges, prototypes, instances }))
+    }
+
+
+    /// Find the attributes of a node, from the start of the section.
+    fn attributes_of(&mut self, index: u32) -> Option<Option<Box<Attributes>>> {
+        self.cursor.u32()?;
+        self.cursor.u32()?;
+        for _ in 0..self.cursor.varint()? {
+            self.cursor.child_list(self.graph.nodes)?;
+        }
+        for _ in 0..self.cursor.varint()? {
+            let node = self.cursor.u32()?;
+            let attributes = self.attributes()?;
+            if node == index { return Some(attributes); }
+        }
+        Some(None)
+    }
+
+
+    /// Read a string number and look it up.
+    fn string(&mut self) -> Option<String> {
+        self.graph.string(self.cursor.u32()?).ok().map(String::from)
+    }
+
+
+    /// Read components.
+    fn components(&mut self) -> Option<Vec<ComponentRef>> {
+        let count = self.cursor.varint()?;
+        let mut components = Vec::new();
+        for _ in 0..count {
+            components.push(ComponentRef::new(self.cursor.u32()?, self.cursor.u32()?));
+        }
+        Some(components)
+    }
+
+
+    /// Read attributes (None if there are none).
+    fn attributes(&mut self) -> Option<Option<Box<Attributes>>> {
+        let count = self.cursor.varint()?;
+        if count == 0 { return Some(None); }
+        let mut attributes = Attributes::new();
+        for _ in 0..count {
+            let key = self.string()?;
+            attributes.insert(key, self.attribute(0)?);
+        }
+        Some(Some(Box::new(attributes)))
+    }
+
+
+    /// Read an attribute. 'depth' is the number of lists it is in.
+    fn attribute(&mut self, depth: usize) -> Option<Attribute> {
+        Some(match self.cursor.byte()? {
+            0 => Attribute::Bool(self.cursor.byte()? != 0),
+            1 => Attribute::Int(unzigzag(self.cursor.varint()?)),
+            2 => Attribute::Float(f64::from_le_bytes(self.cursor.bytes(8)?.try_into().ok()?)),
+            3 => Attribute::String(self.string()?),
+            4 if depth < MAX_DEPTH => {
+                let count = self.cursor.varint()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.attribute(depth + 1)?);
+                }
+                Attribute::List(values)
+            },
+            _ => return None,
+        })
+    }
+}
+
+
+///
+/// Binary implementation for MachGraph.
+/// A compact alternative to to_json for large graphs, which can also be queried in place with BinaryGraph.
+/// Typed components (the store) and hooks are not part of the file.
+///
+impl<T> MachGraph<T> {
+    /// Write this graph in the binary format.
+    /// Like to_json, a graph whose children form a cycle isn't written, as from_binary would reject it.
+    pub fn to_binary(&self) -> Result<Vec<u8>, GraphError> where T: Serialize {
+        if let Some(cycle) = self.detect_cycles().into_iter().next() { return Err(GraphError::Cycle(cycle)); }
+        Writer::write(self)
+    }
+
+
+    /// Read a graph written by to_binary.
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, GraphError> where T: DeserializeOwned + Default {
+        BinaryGraph::new_unchecked(bytes)?.to_graph()
+    }
+}
+
+
+///
+/// BinaryFile.
+/// A memory-mapped binary graph file.
+///
+#[cfg(feature = "mmap")]
+pub struct BinaryFile {
+    map: memmap2::Mmap,
+}
+
+
+///
+/// Implementation for BinaryFile.
+///
+#[cfg(feature = "mmap")]
+impl BinaryFile {
+    /// Map a binary graph file.
+    ///
+    /// # Safety
+    /// The file must not be changed while it is mapped (see memmap2::Mmap::map).
+    pub unsafe fn open(path: impl AsRef<std::path::Path>) -> Result<Self, GraphError> {
+        let path = path.as_ref();
+        let error = |error: std::io::Error| GraphError::Io(format!("{}: {}", path.display(), error));
+        let file = std::fs::File::open(path).map_err(error)?;
+        let map = memmap2::Mmap::map(&file).map_err(error)?;
+        Ok(Self { map })
+    }
+
+
+    /// Reader over the mapped file, after checking its checksum.
+    pub fn graph(&self) -> Result<BinaryGraph<'_>, GraphError> {
+        BinaryGraph::new(&self.map)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use crate::dag::{Visitor, MachNode, Handle};
+
+
+///
+/// Chain visitor.
+/// Runs two visitors in one pass, 'first' before 'second' on every node.
+///
+#[derive(Debug, Default)]
+pub struct Chain<A, B> {
+    /// First visitor.
+    pub first: A,
+
+    /// Second visitor.
+    pub second: B,
+}
+
+
+///
+/// Visitor implementation for Chain.
+/// 
+impl<T, A: Visitor<T>, B: Visitor<T>> Visitor<T> for Chain<A, B> {
+    fn visit(&self, node: &MachNode<T>) {
+        self.first.visit(node);
+        self.second.visit(node);
+    }
+
+    fn visit_mut(&mut self, node: &mut MachNode<T>) {
+        self.first.visit_mut(node);
+        self.second.visit_mut(node);
+    }
+}
+
+
+///
+/// Filter visitor.
+/// Only passes nodes matching a predicate to the inner visitor.
+///
+#[derive(Debug, Default)]
+pub struct Filter<V, P> {
+    /// Inner visitor.
+    pub inner: V,
+
+    /// Predicate deciding which nodes are visited.
+    pub predicate: P,
+}
+
+
+///
+/// Visitor implementation for Filter.
+/// 
+impl<T, V: Visitor<T>, P: Fn(&MachNode<T>) -> bool> Visitor<T> for Filter<V, P> {
+    fn visit(&self, node: &MachNode<T>) {
+        if (self.predicate)(node) { self.inner.visit(node); }
+    }
+
+    fn visit_mut(&mut self, node: &mut MachNode<T>) {
+        if (self.predicate)(node) { self.inner.visit_mut(node); }
+    }
+}
+
+
+///
+/// Collect visitor.
+/// Maps every visited node to a value and collects the values in visit order.
+/// Works with both the immutable and the mutable visits, and with the parallel ones (it is Sync when R and F are).
+///
+#[derive(Debug)]
+pub struct Collect<R, F> {
+    items: Mutex<Vec<R>>,
+    map: F,
+}
+
+
+///
+/// Collect implementation.
+/// 
+impl<R, F> Collect<R, F> {
+    /// New collector mapping nodes with 'map'.
+    pub fn new<T>(map: F) -> Self where F: Fn(&MachNode<T>) -> R {
+        Self {
+            items: Mutex::new(Vec::new()),
+            map,
+        }
+    }
+
+
+    /// Collected values.
+    pub fn items(&self) -> MutexGuard<'_, Vec<R>> {
+        self.items.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+
+    /// Take the collected values.
+    pub fn into_items(self) -> Vec<R> {
+        self.items.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+///
+/// Ready-made collectors.
+/// 
+impl<T> Collect<String, fn(&MachNode<T>) -> String> {
+    /// Collect node names.
+    pub fn names() -> Self {
+        Self::new(|node| node.name.clone())
+    }
+}
+impl<T> Collect<Handle, fn(&MachNode<T>) -> Handle> {
+    /// Collect node handles (name and index).
+    pub fn handles() -> Self {
+        Self::new(|node| Handle::from((node.name.as_str(), node.index)))
+    }
+}
+impl<T> Collect<u32, fn(&MachNode<T>) -> u32> {
+    /// Collect node indices.
+    pub fn indices() -> Self {
+        Self::new(|node| node.index)
+    }
+}
+
+
+///
+/// Visitor implementation for Collect.
+/// 
+impl<T, R, F: Fn(&MachNode<T>) -> R> Visitor<T> for Collect<R, F> {
+    fn visit(&self, node: &MachNode<T>) {
+        let item = (self.map)(node);
+        self.items().push(item);
+    }
+}
+
+
+///
+/// CountByDepth visitor.
+/// Counts visited nodes per depth. Depth is relative to the shallowest visited nodes
+/// (nodes whose parent wasn't visited are depth 0), so it works for any visit order and start node,
+/// including the parallel visits.
+///
+#[derive(Debug, Default)]
+pub struct CountByDepth {
+    parents: Mutex<HashMap<u32, Option<u32>>>,
+}
+
+
+///
+/// CountByDepth implementation.
+/// 
+impl CountByDepth {
+    /// Number of visited nodes.
+    pub fn total(&self) -> usize {
+        self.parents().len()
+    }
+
+
+    /// Node counts indexed by depth.
+    pub fn counts(&self) -> Vec<usize> {
+        let parents = self.parents();
+        let mut depths: HashMap<u32, usize> = HashMap::with_capacity(parents.len());
+        let mut counts = Vec::new();
+        for index in parents.keys() {
+            // Walk up until a known depth or an unvisited parent, then fill in on the way back.
+            let mut chain = vec![*index];
+            let mut depth = loop {
+                let current = *chain.last().unwrap();
+                if let Some(depth) = depths.get(&current) { chain.pop(); break *depth + 1; }
+                match parents.get(&current).copied().flatten() {
+                    Some(parent) if parents.contains_key(&parent) && chain.len() <= parents.len() => chain.push(parent),
+                    _ => break 0,
+                }
+            };
+            for index in chain.into_iter().rev() {
+                depths.insert(index, depth);
+                if counts.len() <= depth { counts.resize(depth + 1, 0); }
+                counts[depth] += 1;
+                depth += 1;
+            }
+        }
+        counts
+    }
+
+
+    /// Visited nodes and their parents.
+    fn parents(&self) -> MutexGuard<'_, HashMap<u32, Option<u32>>> {
+        self.parents.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+
+///
+/// Visitor implementation for CountByDepth.
+/// 
+impl<T> Visitor<T> for CountByDepth {
+    fn visit(&self, node: &MachNode<T>) {
+        let parent = if node.has_parent() { Some(node.parent) } else { None };
+        self.parents().insert(node.index, parent);
+    }
+}
\ No newline at end of file
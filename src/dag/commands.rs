@@ -0,0 +1,234 @@
+use super::{MachGraph, MachNode, Handle, ComponentRef, GraphError};
+
+
+///
+/// CommandHandle.
+/// Refers to a node from a command buffer - either an existing node or one created by the buffer.
+/// Created nodes resolve to a real Handle after the buffer is applied.
+///
+#[derive(Debug, Clone)]
+pub enum CommandHandle {
+    /// Existing node in the graph.
+    Node(Handle),
+
+    /// Node created by the buffer (position in the buffer's created list).
+    Pending(usize),
+}
+
+
+///
+/// From a handle.
+/// 
+impl From<Handle> for CommandHandle {
+    fn from(handle: Handle) -> Self {
+        CommandHandle::Node(handle)
+    }
+}
+
+
+///
+/// From a handle reference.
+/// 
+impl From<&Handle> for CommandHandle {
+    fn from(handle: &Handle) -> Self {
+        CommandHandle::Node(handle.clone())
+    }
+}
+
+
+///
+/// From a command handle reference.
+/// 
+impl From<&CommandHandle> for CommandHandle {
+    fn from(handle: &CommandHandle) -> Self {
+        handle.clone()
+    }
+}
+
+
+///
+/// Recorded structural edit.
+/// 
+#[derive(Debug, Clone)]
+enum Command {
+    PushChild { name: String, parent: Option<CommandHandle> },
+    Remove { node: CommandHandle },
+    Reparent { node: CommandHandle, parent: CommandHandle },
+    PushComponent { node: CommandHandle, component: ComponentRef },
+}
+
+
+///
+/// GraphCommands.
+/// Buffer of structural edits recorded while a graph is borrowed (during a traversal),
+/// applied in recorded order in one go once the traversal has released the graph.
+/// Applying is all or nothing: when a command refers to a missing node or can't be applied, no command is.
+///
+#[derive(Debug, Default)]
+pub struct GraphCommands {
+    commands: Vec<Command>,
+    pending: usize,
+}
+
+
+///
+/// GraphCommands implementation.
+/// 
+impl GraphCommands {
+    /// New empty command buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /// Number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+
+    /// No commands recorded?
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+
+    /// Record pushing a child of root with a name.
+    pub fn push_child(&mut self, name: &str) -> CommandHandle {
+        self.push_child_internal(name, None)
+    }
+
+
+    /// Record pushing a child with a name and a parent (existing or created by this buffer).
+    pub fn push_child_of(&mut self, name: &str, parent: impl Into<CommandHandle>) -> CommandHandle {
+        self.push_child_internal(name, Some(parent.into()))
+    }
+    fn push_child_internal(&mut self, name: &str, parent: Option<CommandHandle>) -> CommandHandle {
+        self.commands.push(Command::PushChild { name: String::from(name), parent });
+        self.pending += 1;
+        CommandHandle::Pending(self.pending - 1)
+    }
+
+
+    /// Record removing a node and its subtree.
+    pub fn remove(&mut self, node: impl Into<CommandHandle>) {
+        self.commands.push(Command::Remove { node: node.into() });
+    }
+
+
+    /// Record moving a node under a new parent.
+    pub fn reparent(&mut self, node: impl Into<CommandHandle>, parent: impl Into<CommandHandle>) {
+        self.commands.push(Command::Reparent { node: node.into(), parent: parent.into() });
+    }
+
+
+    /// Record pushing a component to a node.
+    pub fn push_component(&mut self, node: impl Into<CommandHandle>, component: impl Into<ComponentRef>) {
+        self.commands.push(Command::PushComponent { node: node.into(), component: component.into() });
+    }
+
+
+    /// Apply all commands to a graph in recorded order.
+    /// The commands are first run on a copy of the graph's hierarchy (without components, hooks or payloads),
+    /// so the graph is left untouched when one of them fails.
+    pub fn apply<T: Default>(self, graph: &mut MachGraph<T>) -> Result<AppliedCommands, GraphError> {
+        let mut hierarchy = MachGraph::new(&graph.name);
+        hierarchy.root = graph.root.clone();
+        hierarchy.nodes = graph.nodes.iter().map(|node| MachNode {
+            name: node.name.clone(),
+            parent: node.parent,
+            index: node.index,
+            children: node.children.clone(),
+            components: Vec::new(),
+            attributes: node.attributes.clone(),
+            removed: node.removed,
+            data: (),
+        }).collect();
+        self.run(&mut hierarchy)?;
+        self.run(graph)
+    }
+
+
+    /// Run all commands on a graph, stopping at the first one that fails.
+    fn run<T: Default>(&self, graph: &mut MachGraph<T>) -> Result<AppliedCommands, GraphError> {
+        let mut applied = AppliedCommands { created: Vec::with_capacity(self.pending) };
+        for (position, command) in self.commands.iter().enumerate() {
+            let fail = |reason: String| GraphError::Command(position, reason);
+            match command {
+                Command::PushChild { name, parent } => {
+                    let parent = match parent {
+                        Some(parent) => applied.live(parent, graph).map_err(fail)?,
+                        None => graph.root.clone(),
+                    };
+                    let created = graph.push_child_with(name, &parent, T::default());
+                    applied.created.push(created.ok_or_else(|| fail(String::from("the root was not found")))?);
+                },
+                Command::Remove { node } => {
+                    let node = applied.live(node, graph).map_err(fail)?;
+                    if !graph.remove(&node) { return Err(fail(String::from("the root can't be removed"))); }
+                },
+                Command::Reparent { node, parent } => {
+                    let node = applied.live(node, graph).map_err(fail)?;
+                    let parent = applied.live(parent, graph).map_err(fail)?;
+                    if !graph.reparent(&node, &parent) {
+                        return Err(fail(format!("{} can't be moved under {}", describe(&node), describe(&parent))));
+                    }
+                },
+                Command::PushComponent { node, component } => {
+                    let node = applied.live(node, graph).map_err(fail)?;
+                    graph.push_component(&node, *component);
+                },
+            }
+        }
+        Ok(applied)
+    }
+}
+
+
+///
+/// AppliedCommands.
+/// Result of applying a command buffer, used to resolve handles of created nodes.
+///
+#[derive(Debug, Default)]
+pub struct AppliedCommands {
+    created: Vec<Handle>,
+}
+
+
+///
+/// AppliedCommands implementation.
+/// 
+impl AppliedCommands {
+    /// Resolve a command handle to a graph handle. 'None' if the node was not created (by this buffer).
+    pub fn resolve(&self, handle: &CommandHandle) -> Option<Handle> {
+        match handle {
+            CommandHandle::Node(handle) => Some(handle.clone()),
+            CommandHandle::Pending(index) => self.created.get(*index).cloned(),
+        }
+    }
+
+
+    /// Handles of created nodes, in recorded order.
+    pub fn created(&self) -> &[Handle] {
+        &self.created
+    }
+
+
+    /// Resolve a command handle to a live node of a graph (or the reason it can't be).
+    fn live<T>(&self, handle: &CommandHandle, graph: &MachGraph<T>) -> Result<Handle, String> {
+        let resolved = self.resolve(handle).ok_or_else(|| String::from("a node of another buffer was used"))?;
+        match graph.get_node(&resolved) {
+            Some(_) => Ok(resolved),
+            None => Err(format!("{} was not found", describe(&resolved))),
+        }
+    }
+}
+
+
+/// Handle for error messages - by index when it has one (like lookups), otherwise by path.
+fn describe(handle: &Handle) -> String {
+    match handle.index {
+        Some(index) => format!("node {}", index),
+        None => format!("node '{}'", handle.path),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use super::Attribute;
+
+
+///
+/// Edge.
+/// A labelled relationship from one node to another, outside the parent/child hierarchy
+/// ("references", "depends_on", "targets"...). Edges can carry a value.
+/// Labels are kept as strings on purpose: files store them as they are, with no label registry to keep in step.
+/// Any 'AsRef<str>' type can be passed as a label, so a graph's relationships can be an enum that names itself.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edge {
+    /// Index of the source node.
+    pub from: u32,
+
+    /// Index of the target node.
+    pub to: u32,
+
+    /// Label (type) of this edge.
+    pub label: String,
+
+    /// Optional data of this edge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Attribute>,
+}
+
+
+///
+/// Implementation for Edge.
+///
+impl Edge {
+    /// New edge without data.
+    pub fn new(from: u32, to: u32, label: impl AsRef<str>) -> Self {
+        Self { from, to, label: String::from(label.as_ref()), data: None }
+    }
+
+
+    /// New edge with data.
+    pub fn with_data(from: u32, to: u32, label: impl AsRef<str>, data: impl Into<Attribute>) -> Self {
+        Self { from, to, label: String::from(label.as_ref()), data: Some(data.into()) }
+    }
+
+
+    /// Does this edge connect these nodes with this label?
+    pub fn is(&self, from: u32, to: u32, label: impl AsRef<str>) -> bool {
+        self.from == from && self.to == to && self.label == label.as_ref()
+    }
+
+
+    /// Does this edge start or end at a node?
+    pub fn touches(&self, index: u32) -> bool {
+        self.from == index || self.to == index
+    }
+}
+
+
+///
+/// Edges.
+/// Labelled edges of a graph, in insertion order.
+/// There is at most one edge per source, target and label. Edges are indexed by source and target node,
+/// so looking up the edges of a node costs only the number of edges it has. Serialized as a list of edges.
+///
+#[derive(Debug, Default, Clone)]
+pub struct Edges {
+    /// Edges by insertion number.
+    list: BTreeMap<u64, Edge>,
+
+    /// Insertion number of the next edge.
+    next: u64,
+
+    /// Insertion numbers of the edges starting at each node.
+    from: BTreeMap<u32, BTreeSet<u64>>,
+
+    /// Insertion numbers of the edges ending at each node.
+    to: BTreeMap<u32, BTreeSet<u64>>,
+}
+
+
+///
+/// Implementation for Edges.
+///
+impl Edges {
+    /// Number of edges.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+
+    /// No edges at all?
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+
+    /// Iterate all edges.
+    pub fn iter(&self) -> impl Iterator<Item = &Edge> {
+        self.list.values()
+    }
+
+
+    /// Insert an edge. Returns false if it already existed (its data is replaced).
+    pub fn insert(&mut self, edge: Edge) -> bool {
+        if let Some(existing) = self.get_mut(edge.from, edge.to, &edge.label) {
+            existing.data = edge.data;
+            return false;
+        }
+        let id = self.next;
+        self.next += 1;
+        self.from.entry(edge.from).or_default().insert(id);
+        self.to.entry(edge.to).or_default().insert(id);
+        self.list.insert(id, edge);
+        true
+    }
+
+
+    /// Remove an edge.
+    pub fn remove(&mut self, from: u32, to: u32, label: impl AsRef<str>) -> Option<Edge> {
+        let id = self.find(from, to, label)?;
+        self.remove_id(id)
+    }
+
+
+    /// Get an edge.
+    pub fn get(&self, from: u32, to: u32, label: impl AsRef<str>) -> Option<&Edge> {
+        self.find(from, to, label).and_then(|id| self.list.get(&id))
+    }
+
+
+    /// Get an edge mutable.
+    pub fn get_mut(&mut self, from: u32, to: u32, label: impl AsRef<str>) -> Option<&mut Edge> {
+        self.find(from, to, label).and_then(|id| self.list.get_mut(&id))
+    }
+
+
+    /// Edges starting at a node.
+    pub fn outgoing(&self, index: u32) -> impl Iterator<Item = &Edge> {
+        self.edges(self.from.get(&index))
+    }
+
+
+    /// Edges ending at a node.
+    pub fn incoming(&self, index: u32) -> impl Iterator<Item = &Edge> {
+        self.edges(self.to.get(&index))
+    }
+
+
+    /// Edges with a label.
+    pub fn labelled(&self, label: impl AsRef<str>) -> impl Iterator<Item = &Edge> {
+        self.iter().filter(move |edge| edge.label == label.as_ref())
+    }
+
+
+    /// Remove every edge starting or ending at a node (when the node is removed).
+    /// Returns the number of edges removed.
+    pub fn release(&mut self, index: u32) -> usize {
+        let mut ids = self.from.remove(&index).unwrap_or_default();
+        ids.extend(self.to.remove(&index).unwrap_or_default());
+        ids.into_iter().filter_map(|id| self.remove_id(id)).count()
+    }
+
+
+    /// Insertion number of an edge, looked up in the smaller of the source's and the target's edges.
+    fn find(&self, from: u32, to: u32, label: impl AsRef<str>) -> Option<u64> {
+        let outgoing = self.from.get(&from)?;
+        let incoming = self.to.get(&to)?;
+        let ids = if outgoing.len() <= incoming.len() { outgoing } else { incoming };
+        ids.iter().copied().find(|id| self.list.get(id).is_some_and(|edge| edge.is(from, to, &label)))
+    }
+
+
+    /// Edges of a set of insertion numbers, in insertion order.
+    fn edges<'a>(&'a self, ids: Option<&'a BTreeSet<u64>>) -> impl Iterator<Item = &'a Edge> {
+        ids.into_iter().flatten().filter_map(|id| self.list.get(id))
+    }
+
+
+    /// Remove an edge by insertion number, unindexing it.
+    fn remove_id(&mut self, id: u64) -> Option<Edge> {
+        let edge = self.list.remove(&id)?;
+        for (index, nodes) in [(edge.from, &mut self.from), (edge.to, &mut self.to)] {
+            if let Some(ids) = nodes.get_mut(&index) {
+                ids.remove(&id);
+                if ids.is_empty() { nodes.remove(&index); }
+            }
+        }
+        Some(edge)
+    }
+}
+
+
+///
+/// Edges are equal when they list the same edges in the same order.
+///
+impl PartialEq for Edges {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+
+///
+/// Build from edges, in order.
+///
+impl FromIterator<Edge> for Edges {
+    fn from_iter<I: IntoIterator<Item = Edge>>(edges: I) -> Self {
+        let mut result = Self::default();
+        for edge in edges { result.insert(edge); }
+        result
+    }
+}
+
+
+///
+/// Serialized as a list of edges.
+///
+impl Serialize for Edges {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+
+///
+/// Deserialized from a list of edges.
+///
+impl<'de> Deserialize<'de> for Edges {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<Edge>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,63 @@
+use std::fmt;
+use super::Handle;
+
+
+///
+/// GraphError.
+/// Errors reported by graph operations that can't just skip bad input.
+///
+#[derive(Debug, Clone)]
+pub enum GraphError {
+    /// The graph contains a cycle (nodes on the cycle, in order).
+    Cycle(Vec<Handle>),
+
+    /// A file can't be read or written (with the reason).
+    Format(String),
+
+    /// A file was written in a format version this release doesn't know.
+    UnsupportedVersion(u32),
+
+    /// A file can't be opened (with the reason).
+    Io(String),
+
+    /// A recorded command can't be applied (its position in the buffer, with the reason).
+    Command(usize, String),
+}
+
+
+///
+/// Implementation for GraphError.
+///
+impl GraphError {
+    /// Invalid file error (with the reason).
+    pub(crate) fn format(reason: impl fmt::Display) -> Self {
+        GraphError::Format(reason.to_string())
+    }
+}
+
+
+///
+/// Display implementation.
+/// 
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Cycle(nodes) => {
+                let names: Vec<String> = nodes.iter()
+                    .map(|node| format!("{}[{}]", node.path, node.index.unwrap_or(u32::MAX)))
+                    .collect();
+                write!(f, "graph contains a cycle: {}", names.join(" -> "))
+            },
+            GraphError::Format(reason) => write!(f, "invalid graph file: {}", reason),
+            GraphError::UnsupportedVersion(version) => write!(f, "unsupported graph file version {}", version),
+            GraphError::Io(reason) => write!(f, "can't open graph file: {}", reason),
+            GraphError::Command(position, reason) => write!(f, "command {} can't be applied: {}", position, reason),
+        }
+    }
+}
+
+
+///
+/// Error implementation.
+/// 
+impl std::error::Error for GraphError {}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use super::{MachGraph, MachNode, Handle, ComponentRef, Attributes, Tags, Edges, Prototype, Instance, GraphError};
+use super::prototype::Synced;
+use super::migrate::migrate;
+use super::node::is_zero_sized;
+
+
+///
+/// Version of the JSON format written by MachGraph::to_json (older versions are migrated on load).
+/// Nodes are listed by index; a node's parent is the first node listing it as a child, so usually only 'children' is written.
+/// Nodes can be listed by more than one node (shared children), and nodes no node lists (added with push) have no parent.
+/// A node whose parent is not the first node listing it (or that has one while not listed) also writes 'parent'.
+/// Removed node slots are written as null so indices stay stable. Empty fields are left out.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "name": "snowman",
+///   "index": 0,
+///   "root": 0,
+///   "nodes": [
+///     { "name": "root", "children": [1] },
+///     { "name": "body", "components": [4, { "kind": 2, "index": 9 }], "attributes": { "color": "white" } },
+///     null
+///   ],
+///   "tags": { "selectable": [1] },
+///   "edges": [{ "from": 1, "to": 0, "label": "references" }],
+///   "prototypes": [{ "graph": { "name": "button", ... }, "synced": [...] }],
+///   "instances": [{ "prototype": 0, "root": 1, "nodes": [1] }]
+/// }
+/// ```
+///
+/// Node payloads are written as "data" (unless zero sized). Prototype graphs use the same shape without a version.
+///
+pub const FORMAT_VERSION: u32 = 1;
+
+
+/// A graph as written (borrowing the graph).
+#[derive(Serialize)]
+#[serde(bound = "T: Serialize")]
+struct GraphOut<'a, T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    name: &'a str,
+    index: u32,
+    root: u32,
+    nodes: Vec<Option<NodeOut<'a, T>>>,
+    #[serde(skip_serializing_if = "Tags::is_empty")]
+    tags: &'a Tags,
+    #[serde(skip_serializing_if = "Edges::is_empty")]
+    edges: &'a Edges,
+    #[serde(skip_serializing_if = "<[PrototypeOut]>::is_empty")]
+    prototypes: Vec<PrototypeOut<'a>>,
+    #[serde(skip_serializing_if = "<[Instance]>::is_empty")]
+    instances: &'a [Instance],
+}
+
+
+/// A node as written.
+#[derive(Serialize)]
+#[serde(bound = "T: Serialize")]
+struct NodeOut<'a, T> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<u32>,
+    #[serde(skip_serializing_if = "<[u32]>::is_empty")]
+    children: &'a [u32],
+    #[serde(skip_serializing_if = "<[ComponentRef]>::is_empty")]
+    components: &'a [ComponentRef],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<&'a Attributes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<&'a T>,
+}
+
+
+/// A prototype as written.
+#[derive(Serialize)]
+struct PrototypeOut<'a> {
+    graph: GraphOut<'a, ()>,
+    synced: &'a [Option<Synced>],
+}
+
+
+/// A graph as read (the version is checked before).
+#[derive(Deserialize)]
+#[serde(bound = "T: Deserialize<'de> + Default")]
+pub(crate) struct GraphIn<T> {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) index: u32,
+    pub(crate) root: u32,
+    pub(crate) nodes: Vec<Option<NodeIn<T>>>,
+    #[serde(default)]
+    pub(crate) tags: Tags,
+    #[serde(default)]
+    pub(crate) edges: Edges,
+    #[serde(default)]
+    pub(crate) prototypes: Vec<PrototypeIn>,
+    #[serde(default)]
+    pub(crate) instances: Vec<Instance>,
+}
+
+
+/// A node as read.
+#[derive(Deserialize)]
+#[serde(bound = "T: Deserialize<'de> + Default")]
+pub(crate) struct NodeIn<T> {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) parent: Option<u32>,
+    #[serde(default)]
+    pub(crate) children: Vec<u32>,
+    #[serde(default)]
+    pub(crate) components: Vec<ComponentRef>,
+    #[serde(default)]
+    pub(crate) attributes: Option<Box<Attributes>>,
+    #[serde(default)]
+    pub(crate) data: T,
+}
+
+
+/// A prototype as read.
+#[derive(Deserialize)]
+pub(crate) struct PrototypeIn {
+    pub(crate) graph: GraphIn<()>,
+    pub(crate) synced: Vec<Option<Synced>>,
+}
+
+
+/// First node listing each node as a child, by index ('children' lists of the nodes, None for removed ones).
+fn listed<'a>(children: impl Iterator<Item = Option<&'a [u32]>>, len: usize) -> Vec<Option<u32>> {
+    let mut listed = vec![None; len];
+    for (index, children) in children.enumerate() {
+        for child in children.into_iter().flatten() {
+            if let Some(first) = listed.get_mut(*child as usize) { first.get_or_insert(index as u32); }
+        }
+    }
+    listed
+}
+
+
+///
+/// From a graph.
+///
+impl<'a, T> From<&'a MachGraph<T>> for GraphOut<'a, T> {
+    fn from(graph: &'a MachGraph<T>) -> Self {
+        let listed = listed(graph.nodes.iter().map(|node| (!node.removed).then_some(node.children.as_slice())), graph.nodes.len());
+        Self {
+            version: None,
+            name: &graph.name,
+            index: graph.index,
+            root: graph.root_index(),
+            nodes: graph.nodes.iter().map(|node| {
+                if node.removed { return None; }
+                Some(NodeOut {
+                    name: &node.name,
+                    parent: (node.parent != listed[node.index as usize].unwrap_or(node.index)).then_some(node.parent),
+                    children: &node.children,
+                    components: &node.components,
+                    attributes: node.attributes.as_deref(),
+                    data: (!is_zero_sized(&node.data)).then_some(&node.data),
+                })
+            }).collect(),
+            tags: &graph.tags,
+            edges: &graph.edges,
+            prototypes: graph.prototypes.iter()
+                .map(|prototype| PrototypeOut { graph: GraphOut::from(&prototype.graph), synced: &prototype.synced })
+                .collect(),
+            instances: &graph.instances,
+        }
+    }
+}
+
+
+///
+/// Implementation for GraphIn.
+///
+impl<T: Default> GraphIn<T> {
+    /// Check the file and build the graph.
+    pub(crate) fn into_graph(self) -> Result<MachGraph<T>, GraphError> {
+        let len = self.nodes.len() as u32;
+        let live = |index: u32| index < len && self.nodes[index as usize].is_some();
+        if !live(self.root) { return Err(GraphError::format(format!("root {} is not a node", self.root))); }
+
+        // Children and written parents are nodes.
+        for (index, node) in self.nodes.iter().enumerate() {
+            let Some(node) = node else { continue; };
+            if let Some(child) = node.children.iter().find(|child| !live(**child)) {
+                return Err(GraphError::format(format!("node {} has child {} which is not a node", index, child)));
+            }
+            if let Some(parent) = node.parent.filter(|parent| !live(*parent)) {
+                return Err(GraphError::format(format!("node {} has parent {} which is not a node", index, parent)));
+            }
+        }
+        let listed = listed(self.nodes.iter().map(|node| node.as_ref().map(|node| node.children.as_slice())), len as usize);
+
+        for tag in self.tags.names() {
+            if let Some(index) = self.tags.nodes(tag).find(|index| !live(*index)) {
+                return Err(GraphError::format(format!("tag '{}' is on {} which is not a node", tag, index)));
+            }
+        }
+        if let Some(edge) = self.edges.iter().find(|edge| !live(edge.from) || !live(edge.to)) {
+            return Err(GraphError::format(format!("edge '{}' from {} to {} is not between nodes", edge.label, edge.from, edge.to)));
+        }
+
+        let mut prototypes = Vec::with_capacity(self.prototypes.len());
+        for prototype in self.prototypes {
+            let graph = prototype.graph.into_graph()?;
+            if prototype.synced.len() != graph.nodes.len() {
+                return Err(GraphError::format(format!("prototype '{}' has {} nodes but {} synced", graph.name, graph.nodes.len(), prototype.synced.len())));
+            }
+            prototypes.push(Prototype { graph, synced: prototype.synced });
+        }
+        for instance in &self.instances {
+            let Some(prototype) = prototypes.get(instance.prototype as usize) else {
+                return Err(GraphError::format(format!("instance of prototype {} which doesn't exist", instance.prototype)));
+            };
+            if instance.nodes.len() != prototype.graph.nodes.len() || !live(instance.root)
+                || instance.nodes.iter().any(|index| *index != u32::MAX && !live(*index)) {
+                return Err(GraphError::format(format!("instance at {} doesn't match prototype {}", instance.root, instance.prototype)));
+            }
+        }
+
+        let nodes = self.nodes.into_iter().enumerate().map(|(index, node)| {
+            let index = index as u32;
+            let parent = node.as_ref().and_then(|node| node.parent).or(listed[index as usize]).unwrap_or(index);
+            match node {
+                Some(node) => MachNode {
+                    name: node.name,
+                    parent,
+                    index,
+                    children: node.children,
+                    components: node.components,
+                    attributes: node.attributes,
+                    removed: false,
+                    data: node.data,
+                },
+                None => MachNode { parent, index, removed: true, ..MachNode::with_data(String::new(), T::default()) },
+            }
+        }).collect::<Vec<MachNode<T>>>();
+
+        let mut graph = MachGraph::with_root(&self.name, T::default());
+        graph.index = self.index;
+        graph.root = Handle::from((nodes[self.root as usize].name.clone(), self.root));
+        graph.nodes = nodes;
+        graph.tags = self.tags;
+        graph.edges = self.edges;
+        graph.prototypes = prototypes;
+        graph.instances = self.instances;
+
+        // Children don't form a cycle (nodes don't have to be reached from the root, see FORMAT_VERSION).
+        if let Some(cycle) = graph.detect_cycles().into_iter().next() { return Err(GraphError::Cycle(cycle)); }
+        Ok(graph)
+    }
+}
+
+
+///
+/// JSON implementation for MachGraph.
+/// Files are versioned (see FORMAT_VERSION) and checked when loaded, so a bad file is an error rather than a broken graph:
+/// the root, children, parents, tags, edges and instances must refer to nodes, and children must not form a cycle.
+/// Graphs with a cycle aren't written either. Typed components (the store) and hooks are not part of the file.
+///
+impl<T> MachGraph<T> {
+    /// Write this graph as JSON.
+    pub fn to_json(&self) -> Result<String, GraphError> where T: Serialize {
+        serde_json::to_string(&self.document()?).map_err(GraphError::format)
+    }
+
+
+    /// Write this graph as indented JSON.
+    pub fn to_json_pretty(&self) -> Result<String, GraphError> where T: Serialize {
+        serde_json::to_string_pretty(&self.document()?).map_err(GraphError::format)
+    }
+
+
+    /// Read a graph from JSON written by to_json.
+    /// Files of older format versions are migrated; files of newer versions are an error.
+    pub fn from_json(json: &str) -> Result<Self, GraphError> where T: DeserializeOwned + Default {
+        let value: Value = serde_json::from_str(json).map_err(GraphError::format)?;
+        let value = migrate(value)?;
+        let file: GraphIn<T> = serde_json::from_value(value).map_err(GraphError::format)?;
+        file.into_graph()
+    }
+
+
+    /// The versioned document of this graph, unless it has a cycle (which from_json would reject).
+    fn document(&self) -> Result<GraphOut<'_, T>, GraphError> {
+        if let Some(cycle) = self.detect_cycles().into_iter().next() { return Err(GraphError::Cycle(cycle)); }
+        Ok(GraphOut { version: Some(FORMAT_VERSION), ..GraphOut::from(self) })
+    }
+}
\ No newline at end of file
@@ -1,14 +1,21 @@
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
-use crate::dag::Visitor;
-use super::{MachNode, Handle};
+use crate::dag::{Visitor, GraphVisitor, CommandVisitor};
+use super::{MachNode, Handle, Attribute, Tags, Edge, Edges, Prototype, Instance, ComponentRef, ComponentHook, ComponentHooks, Component, ComponentStore, GraphError, GraphView, GraphCommands, AppliedCommands, Ancestors, Descendants, Siblings, Leaves};
+use super::walk::{Walk, Step};
+use super::refs::ComponentRefs;
+use super::prototype::InstanceNodes;
+use super::path::segments;
 
 
 ///
 /// MachGraph.
 /// This is where nodes are organized.
+/// Nodes can carry a payload 'T' (none by default), serialized with the graph when 'T' supports it.
 /// 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MachGraph {
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de> + Default"))]
+pub struct MachGraph<T = ()> {
     /// Name of this graph.
     pub name: String,
 
@@ -19,7 +26,39 @@ pub struct MachGraph {
     pub root: Handle,
 
     /// Nodes in this graph.
-    pub nodes: Vec<MachNode>,
+    pub nodes: Vec<MachNode<T>>,
+
+    /// Tags of nodes (index from tag to nodes).
+    #[serde(default, skip_serializing_if = "Tags::is_empty")]
+    pub tags: Tags,
+
+    /// Labelled edges between nodes, besides the hierarchy.
+    #[serde(default, skip_serializing_if = "Edges::is_empty")]
+    pub edges: Edges,
+
+    /// Prototype subtrees that can be instantiated in this graph.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prototypes: Vec<Prototype>,
+
+    /// Instances of prototypes in this graph (edit them through the prototype methods, which keep the node lookup up to date).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<Instance>,
+
+    /// Typed component data owned by this graph (not serialized).
+    #[serde(skip)]
+    pub store: ComponentStore,
+
+    /// Hooks run when components are added to or removed from nodes (not serialized).
+    #[serde(skip)]
+    pub hooks: ComponentHooks<T>,
+
+    /// Reference counts of components (rebuilt on demand, not serialized).
+    #[serde(skip)]
+    refs: ComponentRefs,
+
+    /// Instance and prototype node of every instance node (rebuilt on demand, not serialized).
+    #[serde(skip)]
+    pub(crate) instance_nodes: InstanceNodes,
 }
 
 
@@ -28,26 +67,42 @@ pub struct MachGraph {
 /// 
 impl Default for MachGraph {
     fn default() -> Self {
-        let root = MachNode::default();
-        Self {
-            name: String::from("default"),
-            index: 0,
-            root: Handle::from((root.name.clone(), 0)),
-            nodes: vec![root],
-        }
+        Self::with_root("default", ())
     }
 }
 
 
 ///
-/// Implementation for MachGraph.
+/// Implementation for MachGraph without payload.
 /// 
 impl MachGraph {
     /// New graph with a name.
     pub fn new(name: &str) -> Self {
+        Self::with_root(name, ())
+    }
+}
+
+
+///
+/// Implementation for MachGraph.
+/// 
+impl<T> MachGraph<T> {
+    /// New graph with a name and the payload of its root node.
+    pub fn with_root(name: &str, data: T) -> Self {
+        let root = MachNode::with_data(String::from("root"), data);
         Self {
             name: String::from(name),
-            ..Default::default()
+            index: 0,
+            root: Handle::from((root.name.clone(), 0)),
+            nodes: vec![root],
+            tags: Tags::default(),
+            edges: Edges::default(),
+            prototypes: Vec::new(),
+            instances: Vec::new(),
+            store: ComponentStore::default(),
+            hooks: ComponentHooks::default(),
+            refs: ComponentRefs::default(),
+            instance_nodes: InstanceNodes::default(),
         }
     }
 
@@ -57,19 +112,19 @@ impl MachGraph {
      **********************************************************/
 
     /// Get root node reference.
-    pub fn get_root(&self) -> Option<&MachNode> {
+    pub fn get_root(&self) -> Option<&MachNode<T>> {
         self.get_node(&self.root)
     }
 
 
     /// Get root node mutable reference.
-    pub fn get_root_mut(&mut self) -> Option<&mut MachNode> {
+    pub fn get_root_mut(&mut self) -> Option<&mut MachNode<T>> {
         self.get_node_mut(&self.root.clone())
     }
 
 
     /// Get node reference.
-    pub fn get_node(&self, handle: &Handle) -> Option<&MachNode> {
+    pub fn get_node(&self, handle: &Handle) -> Option<&MachNode<T>> {
         let index: u32;
         if handle.has_index() { index = handle.index.unwrap(); }
         else { 
@@ -80,7 +135,7 @@ impl MachGraph {
             }
         }
         let u = index as usize;
-        if u < self.nodes.len() {
+        if u < self.nodes.len() && !self.nodes[u].removed {
             return Some(&self.nodes[u]);
         }
         None
@@ -88,7 +143,7 @@ impl MachGraph {
 
 
     /// Get node mutable reference.
-    pub fn get_node_mut(&mut self, handle: &Handle) -> Option<&mut MachNode> {
+    pub fn get_node_mut(&mut self, handle: &Handle) -> Option<&mut MachNode<T>> {
         let index: u32;
         if handle.has_index() { index = handle.index.unwrap(); }
         else { 
@@ -99,7 +154,7 @@ impl MachGraph {
             }
         }
         let u = index as usize;
-        if u < self.nodes.len() {
+        if u < self.nodes.len() && !self.nodes[u].removed {
             return Some(&mut self.nodes[u]);
         }
         None
@@ -107,7 +162,7 @@ impl MachGraph {
 
 
     /// Get parent.
-    pub fn get_parent(&self, handle: &Handle) -> Option<&MachNode> {
+    pub fn get_parent(&self, handle: &Handle) -> Option<&MachNode<T>> {
         if let Some(node) = self.get_node(handle) {
             if !node.has_parent() { return None; }
             return self.get_node(&Handle::from(node.parent));
@@ -116,15 +171,420 @@ impl MachGraph {
     }
 
 
+    /// Get the payload of a node.
+    pub fn get_data(&self, handle: &Handle) -> Option<&T> {
+        self.get_node(handle).map(|node| &node.data)
+    }
+
+
+    /// Get the payload of a node mutable.
+    pub fn get_data_mut(&mut self, handle: &Handle) -> Option<&mut T> {
+        self.get_node_mut(handle).map(|node| &mut node.data)
+    }
+
+
+    /**********************************************************
+     * Relationships
+     **********************************************************/
+
+    /// Ancestors of a node, from its parent up to the root.
+    pub fn ancestors(&self, handle: &Handle) -> Ancestors<'_, T> {
+        Ancestors::new(&self.nodes, self.node_index(handle))
+    }
+
+
+    /// Descendants of a node in pre-order (node not included).
+    pub fn descendants(&self, handle: &Handle) -> Descendants<'_, T> {
+        Descendants::new(&self.nodes, self.node_index(handle))
+    }
+
+
+    /// Siblings of a node (node not included).
+    pub fn siblings(&self, handle: &Handle) -> Siblings<'_, T> {
+        Siblings::new(&self.nodes, self.node_index(handle))
+    }
+
+
+    /// Leaves of this graph (nodes under root without children) in pre-order.
+    pub fn leaves(&self) -> Leaves<'_, T> {
+        Leaves::new(&self.nodes, self.node_index(&self.root))
+    }
+
+
+    /// Depth of a node (root is 0).
+    pub fn depth(&self, handle: &Handle) -> Option<u32> {
+        self.get_node(handle)?;
+        Some(self.ancestors(handle).count() as u32)
+    }
+
+
+    /// Number of nodes in a subtree, including the node itself (0 if not found).
+    pub fn subtree_size(&self, handle: &Handle) -> usize {
+        if self.get_node(handle).is_none() { return 0; }
+        1 + self.descendants(handle).count()
+    }
+
+
+    /// Is 'ancestor' an ancestor of 'node'? A node is not its own ancestor.
+    pub fn is_ancestor_of(&self, ancestor: &Handle, node: &Handle) -> bool {
+        match ancestor.get_index(self) {
+            Some(index) => self.ancestors(node).any(|parent| parent.index == index),
+            None => false,
+        }
+    }
+
+
+    /// Lowest common ancestor of two nodes. A node counts as its own ancestor here.
+    pub fn lowest_common_ancestor(&self, a: &Handle, b: &Handle) -> Option<&MachNode<T>> {
+        let a = self.get_node(a)?;
+        let b = self.get_node(b)?;
+        let mut in_a = vec![false; self.nodes.len()];
+        for node in std::iter::once(a).chain(Ancestors::new(&self.nodes, a.index)) {
+            in_a[node.index as usize] = true;
+        }
+        std::iter::once(b).chain(Ancestors::new(&self.nodes, b.index))
+            .find(|node| in_a.get(node.index as usize).copied().unwrap_or(false))
+    }
+
+
+    /// Index for a handle, or an out of range index when not found (iterators are then empty).
+    fn node_index(&self, handle: &Handle) -> u32 {
+        handle.get_index(self).unwrap_or(u32::MAX)
+    }
+
+
     /**********************************************************
      * Components
      **********************************************************/
 
-    /// Push a component to a node.
-    pub fn push_component(&mut self, node: &Handle, component: u32) {
-        if let Some(node) = self.get_node_mut(node) {
-            node.components.push(component);
+    /// Push a component to a node (a plain index or a typed ComponentRef).
+    /// Fails if the node doesn't exist or already has the component.
+    pub fn push_component(&mut self, node: &Handle, component: impl Into<ComponentRef>) -> bool {
+        let component = component.into();
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        self.refs.prepare(&self.nodes);
+        let node = &mut self.nodes[index as usize];
+        if node.components.contains(&component) { return false; }
+        node.components.push(component);
+        self.refs.add(component);
+        self.hooks.added(node, component);
+        true
+    }
+
+
+    /// Remove a component from a node.
+    pub fn remove_component(&mut self, node: &Handle, component: impl Into<ComponentRef>) -> bool {
+        let component = component.into();
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        self.refs.prepare(&self.nodes);
+        let node = &mut self.nodes[index as usize];
+        let Some(position) = node.components.iter().position(|c| *c == component) else { return false; };
+        node.components.remove(position);
+        self.refs.release(component);
+        self.hooks.removed(node, component);
+        true
+    }
+
+
+    /// Replace a component of a node in place (keeps its position).
+    /// Fails if the node doesn't have the old component or already has the new one.
+    pub fn replace_component(&mut self, node: &Handle, old: impl Into<ComponentRef>, new: impl Into<ComponentRef>) -> bool {
+        let (old, new) = (old.into(), new.into());
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        self.refs.prepare(&self.nodes);
+        let node = &mut self.nodes[index as usize];
+        if old == new { return node.components.contains(&old); }
+        if node.components.contains(&new) { return false; }
+        let Some(position) = node.components.iter().position(|c| *c == old) else { return false; };
+        node.components[position] = new;
+        self.refs.release(old);
+        self.refs.add(new);
+        self.hooks.removed(node, old);
+        self.hooks.added(node, new);
+        true
+    }
+
+
+    /// Remove all components of a node, returning them.
+    pub fn clear_components(&mut self, node: &Handle) -> Vec<ComponentRef> {
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return Vec::new(); };
+        self.take_components(index)
+    }
+
+
+    /// Set the components of a node, in order. Only components that change run hooks.
+    /// A component listed more than once is kept at its first position.
+    pub fn set_components(&mut self, node: &Handle, mut components: Vec<ComponentRef>) -> bool {
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        let mut seen = HashSet::with_capacity(components.len());
+        components.retain(|component| seen.insert(*component));
+        let handle = Handle::from(index);
+        let current = self.nodes[index as usize].components.clone();
+        for component in current.iter().filter(|component| !components.contains(component)) {
+            self.remove_component(&handle, *component);
+        }
+        for component in &components {
+            self.push_component(&handle, *component);
         }
+        self.nodes[index as usize].components = components;
+        true
+    }
+
+
+    /// Does a node have a component?
+    pub fn contains_component(&self, node: &Handle, component: impl Into<ComponentRef>) -> bool {
+        let component = component.into();
+        self.get_node(node).is_some_and(|node| node.components.contains(&component))
+    }
+
+
+    /// Register a hook run after a component of a type is added to a node.
+    pub fn on_add(&mut self, kind: u32, hook: impl FnMut(&MachNode<T>, ComponentRef) + Send + Sync + 'static) {
+        self.hooks.on_add(kind, Box::new(hook) as ComponentHook<T>);
+    }
+
+
+    /// Register a hook run after a component of a type is removed from a node
+    /// (also when the node itself is removed).
+    pub fn on_remove(&mut self, kind: u32, hook: impl FnMut(&MachNode<T>, ComponentRef) + Send + Sync + 'static) {
+        self.hooks.on_remove(kind, Box::new(hook) as ComponentHook<T>);
+    }
+
+
+    /// Number of nodes referencing a component.
+    pub fn ref_count(&self, component: impl Into<ComponentRef>) -> usize {
+        let component = component.into();
+        self.refs.get(&component).unwrap_or_else(|| {
+            self.nodes.iter()
+                .filter(|node| !node.removed)
+                .map(|node| node.components.iter().filter(|c| **c == component).count())
+                .sum()
+        })
+    }
+
+
+    /// Reference counts of all referenced components.
+    pub fn ref_counts(&mut self) -> &HashMap<ComponentRef, usize> {
+        self.refs.prepare(&self.nodes);
+        self.refs.counts().expect("counts are prepared")
+    }
+
+
+    /// Rebuild reference counts (needed after editing 'node.components' directly).
+    pub fn recount_components(&mut self) {
+        self.refs.recount(&self.nodes);
+    }
+
+
+    /// Components no node references any more, since the last collection.
+    /// Each is returned once so its data can be freed from the external store.
+    /// Typed components (see 'store') don't need collecting - they are dropped with their node.
+    pub fn collect_garbage(&mut self) -> Vec<ComponentRef> {
+        self.refs.collect()
+    }
+
+
+    /// Take the components out of a node, running remove hooks.
+    fn take_components(&mut self, index: u32) -> Vec<ComponentRef> {
+        self.refs.prepare(&self.nodes);
+        let node = &mut self.nodes[index as usize];
+        let components = std::mem::take(&mut node.components);
+        for component in &components {
+            self.refs.release(*component);
+            self.hooks.removed(node, *component);
+        }
+        components
+    }
+
+
+    /// Indices of the components of a type on a node, in order.
+    pub fn components_of_type(&self, node: &Handle, kind: u32) -> impl Iterator<Item = u32> + '_ {
+        self.get_node(node).into_iter().flat_map(move |node| node.components_of_type(kind))
+    }
+
+
+    /// Insert a typed component for a node, returning the one it replaces.
+    /// Returns the value back as an error when the node doesn't exist.
+    pub fn insert_component<C: Component>(&mut self, node: &Handle, value: C) -> Result<Option<C>, C> {
+        match self.get_node(node) {
+            Some(node) => Ok(self.store.insert(node.index, value)),
+            None => Err(value),
+        }
+    }
+
+
+    /// Get a typed component of a node.
+    pub fn get_component<C: Component>(&self, node: &Handle) -> Option<&C> {
+        self.store.get(self.get_node(node)?.index)
+    }
+
+
+    /// Get a typed component of a node mutable.
+    pub fn get_component_mut<C: Component>(&mut self, node: &Handle) -> Option<&mut C> {
+        let index = self.get_node(node)?.index;
+        self.store.get_mut(index)
+    }
+
+
+    /// Take a typed component away from a node.
+    pub fn take_component<C: Component>(&mut self, node: &Handle) -> Option<C> {
+        let index = self.get_node(node)?.index;
+        self.store.remove(index)
+    }
+
+
+    /**********************************************************
+     * Attributes
+     **********************************************************/
+
+    /// Get an attribute of a node.
+    pub fn get_attribute(&self, node: &Handle, key: &str) -> Option<&Attribute> {
+        self.get_node(node)?.attribute(key)
+    }
+
+
+    /// Set an attribute of a node, returning the value it replaces.
+    pub fn set_attribute(&mut self, node: &Handle, key: &str, value: impl Into<Attribute>) -> Option<Attribute> {
+        self.get_node_mut(node)?.set_attribute(key, value)
+    }
+
+
+    /// Remove an attribute of a node.
+    pub fn remove_attribute(&mut self, node: &Handle, key: &str) -> Option<Attribute> {
+        self.get_node_mut(node)?.remove_attribute(key)
+    }
+
+
+    /// All nodes whose path ends with a path query, in index order.
+    /// Segments can be '*' for any name and filter on attributes: 'mid.*[color=red][shiny]'.
+    pub fn select(&self, path: &str) -> Vec<Handle> {
+        let segments = segments(path);
+        self.nodes.iter()
+            .filter(|node| !node.removed)
+            .filter(|node| {
+                let mut current = *node;
+                for (position, segment) in segments.iter().enumerate().rev() {
+                    if !segment.matches(current) { return false; }
+                    if position == 0 { break; }
+                    if !current.has_parent() { return false; }
+                    match self.nodes.get(current.parent as usize) {
+                        Some(parent) => current = parent,
+                        None => return false,
+                    }
+                }
+                true
+            })
+            .map(Handle::from)
+            .collect()
+    }
+
+
+    /**********************************************************
+     * Tags
+     **********************************************************/
+
+    /// Tag a node. Returns false if the node doesn't exist or already has the tag.
+    pub fn tag(&mut self, node: &Handle, tag: &str) -> bool {
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        self.tags.insert(index, tag)
+    }
+
+
+    /// Untag a node.
+    pub fn untag(&mut self, node: &Handle, tag: &str) -> bool {
+        let Some(index) = self.get_node(node).map(|node| node.index) else { return false; };
+        self.tags.remove(index, tag)
+    }
+
+
+    /// Does a node have a tag?
+    pub fn has_tag(&self, node: &Handle, tag: &str) -> bool {
+        self.get_node(node).is_some_and(|node| self.tags.contains(node.index, tag))
+    }
+
+
+    /// Nodes with a tag, in index order.
+    pub fn tagged(&self, tag: &str) -> impl Iterator<Item = &MachNode<T>> {
+        self.tags.nodes(tag).filter_map(|index| self.nodes.get(index as usize))
+    }
+
+
+    /**********************************************************
+     * Edges
+     **********************************************************/
+
+    /// Connect two nodes with a labelled edge.
+    /// Returns false if a node doesn't exist or the edge already exists.
+    pub fn connect(&mut self, from: &Handle, to: &Handle, label: impl AsRef<str>) -> bool {
+        let Some((from, to)) = self.edge_ends(from, to) else { return false; };
+        if self.edges.get(from, to, &label).is_some() { return false; }
+        self.edges.insert(Edge::new(from, to, label))
+    }
+
+
+    /// Connect two nodes with a labelled edge carrying data.
+    /// The data of an existing edge is replaced. Returns false if a node doesn't exist.
+    pub fn connect_with(&mut self, from: &Handle, to: &Handle, label: impl AsRef<str>, data: impl Into<Attribute>) -> bool {
+        let Some((from, to)) = self.edge_ends(from, to) else { return false; };
+        self.edges.insert(Edge::with_data(from, to, label, data));
+        true
+    }
+
+
+    /// Remove a labelled edge between two nodes.
+    pub fn disconnect(&mut self, from: &Handle, to: &Handle, label: impl AsRef<str>) -> bool {
+        let Some((from, to)) = self.edge_ends(from, to) else { return false; };
+        self.edges.remove(from, to, label).is_some()
+    }
+
+
+    /// Get a labelled edge between two nodes.
+    pub fn edge(&self, from: &Handle, to: &Handle, label: impl AsRef<str>) -> Option<&Edge> {
+        let (from, to) = self.edge_ends(from, to)?;
+        self.edges.get(from, to, label)
+    }
+
+
+    /// Get a labelled edge between two nodes mutable.
+    pub fn edge_mut(&mut self, from: &Handle, to: &Handle, label: impl AsRef<str>) -> Option<&mut Edge> {
+        let (from, to) = self.edge_ends(from, to)?;
+        self.edges.get_mut(from, to, label)
+    }
+
+
+    /// Edges starting at a node (outgoing).
+    pub fn edges_from(&self, node: &Handle) -> impl Iterator<Item = &Edge> {
+        let index = self.get_node(node).map(|node| node.index);
+        index.into_iter().flat_map(|index| self.edges.outgoing(index))
+    }
+
+
+    /// Edges ending at a node (incoming).
+    pub fn edges_to(&self, node: &Handle) -> impl Iterator<Item = &Edge> {
+        let index = self.get_node(node).map(|node| node.index);
+        index.into_iter().flat_map(|index| self.edges.incoming(index))
+    }
+
+
+    /// Nodes a node points to with a label, in edge order.
+    pub fn targets(&self, node: &Handle, label: impl AsRef<str>) -> impl Iterator<Item = &MachNode<T>> {
+        self.edges_from(node)
+            .filter(move |edge| edge.label == label.as_ref())
+            .filter_map(|edge| self.nodes.get(edge.to as usize))
+    }
+
+
+    /// Nodes pointing to a node with a label, in edge order.
+    pub fn sources(&self, node: &Handle, label: impl AsRef<str>) -> impl Iterator<Item = &MachNode<T>> {
+        self.edges_to(node)
+            .filter(move |edge| edge.label == label.as_ref())
+            .filter_map(|edge| self.nodes.get(edge.from as usize))
+    }
+
+
+    /// Indices of both ends of an edge, if both nodes exist.
+    fn edge_ends(&self, from: &Handle, to: &Handle) -> Option<(u32, u32)> {
+        Some((self.get_node(from)?.index, self.get_node(to)?.index))
     }
 
 
@@ -133,120 +593,508 @@ impl MachGraph {
      **********************************************************/
 
     /// Push a child node of root with a name.
-    pub fn push_child(&mut self, name: &str) -> Handle {
+    pub fn push_child(&mut self, name: &str) -> Handle where T: Default {
         self.push_child_of(name, &self.root.clone())
     }
 
 
     /// Push a new child node with a name and a parent.
-    pub fn push_child_of(&mut self, name: &str, parent: &Handle) -> Handle {
-        if let Some(parent_index) = parent.get_index(self) {
-            let mut node = MachNode::from((name.into(), parent_index));
-            let index = self.nodes.len() as u32;
-            node.index = index;
-            if let Some(parent) = self.get_node_mut(parent) {
-                parent.children.push(node.index);
+    /// When the parent doesn't exist nothing is pushed and a handle to the root is returned (see push_child_with).
+    pub fn push_child_of(&mut self, name: &str, parent: &Handle) -> Handle where T: Default {
+        self.push_child_with(name, parent, T::default()).unwrap_or_else(|| Handle::from("root"))
+    }
+
+
+    /// Push a new child node with a name, a parent and a payload.
+    /// None if the parent doesn't exist or was removed.
+    pub fn push_child_with(&mut self, name: &str, parent: &Handle, data: T) -> Option<Handle> {
+        let parent = parent.get_index(self)?;
+        let index = self.nodes.len() as u32;
+        let mut node = MachNode::with_data(name.into(), data);
+        node.parent = parent;
+        node.index = index;
+        self.nodes[parent as usize].children.push(index);
+        self.nodes.push(node);
+        let mut handle = Handle::from(index);
+        handle.set_path(self);
+        Some(handle)
+    }
+
+
+    /// Remove a node and its subtree. The root can't be removed.
+    /// Slots of removed nodes are kept (marked 'removed') so other nodes' indices and handles stay valid.
+    /// The node is unlinked from every parent; descendants still reached from the root through another parent
+    /// (shared children, links back up the tree) are kept, and get a live parent.
+    /// Components of removed nodes are removed (running remove hooks), their typed components dropped and their tags and edges removed.
+    pub fn remove(&mut self, handle: &Handle) -> bool {
+        let index = match self.get_node(handle) {
+            Some(node) if node.index != self.root_index() => node.index,
+            _ => return false,
+        };
+        self.refs.prepare(&self.nodes);
+        for node in self.nodes.iter_mut() {
+            node.children.retain(|child| *child != index);
+        }
+        self.nodes[index as usize].parent = index;
+        let reached = self.reached();
+        let removed: Vec<u32> = Descendants::inclusive(&self.nodes, index)
+            .map(|node| node.index)
+            .filter(|index| !reached[*index as usize])
+            .collect();
+        for index in removed {
+            self.nodes[index as usize].removed = true;
+            self.take_components(index);
+            self.store.release(index);
+            self.tags.release(index);
+            self.edges.release(index);
+            self.instance_nodes.release(index);
+        }
+        let mut listed = vec![u32::MAX; self.nodes.len()];
+        for parent in self.nodes.iter().filter(|node| !node.removed) {
+            for child in &parent.children {
+                let Some(first) = listed.get_mut(*child as usize) else { continue; };
+                if *first == u32::MAX { *first = parent.index; }
             }
-            self.nodes.push(node);
-            let mut handle = Handle::from(index);
-            handle.set_path(self);
-            return handle;
         }
-        Handle::from("root")
+        let orphans: Vec<u32> = self.nodes.iter()
+            .filter(|node| !node.removed && self.nodes.get(node.parent as usize).is_some_and(|parent| parent.removed))
+            .map(|node| node.index)
+            .collect();
+        for index in orphans {
+            let parent = listed[index as usize];
+            self.nodes[index as usize].parent = if parent == u32::MAX { index } else { parent };
+        }
+        true
+    }
+
+
+    /// Move a node (with its subtree) under a new parent.
+    /// Fails for the root, or when the new parent is the node itself or one of its descendants.
+    pub fn reparent(&mut self, handle: &Handle, parent: &Handle) -> bool {
+        let index = match self.get_node(handle) {
+            Some(node) if node.index != self.root_index() => node.index,
+            _ => return false,
+        };
+        let parent = match self.get_node(parent) {
+            Some(parent) => parent.index,
+            None => return false,
+        };
+        if parent == index || self.is_ancestor_of(&Handle::from(index), &Handle::from(parent)) { return false; }
+        self.detach(index);
+        self.nodes[parent as usize].children.push(index);
+        self.nodes[index as usize].parent = parent;
+        true
+    }
+
+
+    /// Live nodes reached from the root, by index.
+    fn reached(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root_index()];
+        while let Some(index) = stack.pop() {
+            match self.nodes.get(index as usize) {
+                Some(node) if !node.removed && !reached[index as usize] => {
+                    reached[index as usize] = true;
+                    stack.extend(&node.children);
+                },
+                _ => {},
+            }
+        }
+        reached
+    }
+
+
+    /// Detach a node from its parent (it then has no parent).
+    fn detach(&mut self, index: u32) {
+        let node = &self.nodes[index as usize];
+        if node.has_parent() {
+            let parent = node.parent as usize;
+            if let Some(parent) = self.nodes.get_mut(parent) {
+                parent.children.retain(|child| *child != index);
+            }
+        }
+        self.nodes[index as usize].parent = index;
     }
 
 
     /// Push a node to this graph. Sets index and returns it. Not used often...
-    pub fn push(&mut self, mut node: MachNode) -> u32 {
+    /// Its components are counted as references (no hooks run).
+    pub fn push(&mut self, mut node: MachNode<T>) -> u32 {
         let index = self.nodes.len() as u32;
         node.index = index;
+        if !node.removed {
+            for component in &node.components { self.refs.add(*component); }
+        }
         self.nodes.push(node);
         index
     }
 
 
+    /**********************************************************
+     * Aggregation
+     **********************************************************/
+
+    /// Fold values from the leaves up to the root (post-order, no recursion).
+    /// Each node starts with 'leaf_fn(node)', then every child's result is merged in with 'combine_fn(node, value, child_value)'.
+    /// Results are indexed by node index - nodes not reachable from root are 'None'.
+    pub fn fold_up<R>(&self, leaf_fn: impl FnMut(&MachNode<T>) -> R, combine_fn: impl FnMut(&MachNode<T>, R, &R) -> R) -> Result<Vec<Option<R>>, GraphError> {
+        self.fold_up_from(&self.root.clone(), leaf_fn, combine_fn)
+    }
+
+
+    /// Fold values from the leaves up to the start node (post-order, no recursion).
+    /// Only the start node and its descendants get a result.
+    pub fn fold_up_from<R>(&self, start: &Handle, mut leaf_fn: impl FnMut(&MachNode<T>) -> R, mut combine_fn: impl FnMut(&MachNode<T>, R, &R) -> R) -> Result<Vec<Option<R>>, GraphError> {
+        let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(self.nodes.len()).collect();
+        let mut walk = self.walk(start, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                let node = &self.nodes[index as usize];
+                let mut value = leaf_fn(node);
+                for child in &node.children {
+                    if let Some(Some(child_value)) = results.get(*child as usize) {
+                        value = combine_fn(node, value, child_value);
+                    }
+                }
+                results[index as usize] = Some(value);
+            }
+        }
+        walk.finish(&self.nodes)?;
+        Ok(results)
+    }
+
+
+    /// Propagate values from the root down to the leaves (pre-order, no recursion).
+    /// The root gets 'f(&root_value, root)' and every child gets 'f(&parent_value, child)'.
+    /// Returning 'None' stops propagation into that node and its subtree (they stay 'None').
+    /// A shared child gets its value from the first parent that reaches it.
+    pub fn propagate_down<V>(&self, root_value: V, f: impl FnMut(&V, &MachNode<T>) -> Option<V>) -> Result<Vec<Option<V>>, GraphError> {
+        self.propagate_down_from(&self.root.clone(), root_value, f)
+    }
+
+
+    /// Propagate values from the start node down to the leaves (pre-order, no recursion).
+    /// Only the start node and its descendants get a result.
+    pub fn propagate_down_from<V>(&self, start: &Handle, value: V, mut f: impl FnMut(&V, &MachNode<T>) -> Option<V>) -> Result<Vec<Option<V>>, GraphError> {
+        let mut results: Vec<Option<V>> = std::iter::repeat_with(|| None).take(self.nodes.len()).collect();
+        let mut walk = self.walk(start, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                let node = &self.nodes[index as usize];
+                let result = match walk.parent() {
+                    Some(parent) => results[parent as usize].as_ref().and_then(|parent| f(parent, node)),
+                    None => f(&value, node),
+                };
+                if result.is_none() { walk.skip_children(); }
+                results[index as usize] = result;
+            }
+        }
+        walk.finish(&self.nodes)?;
+        Ok(results)
+    }
+
+
     /**********************************************************
      * Visitors
      **********************************************************/
 
     /// Visit all nodes (not in graph order).
-    pub fn visit_all(&self, visitor: &impl Visitor) {
-        for node in &self.nodes { node.accept(visitor); }
+    pub fn visit_all(&self, visitor: &impl Visitor<T>) {
+        for node in self.nodes.iter().filter(|node| !node.removed) { node.accept(visitor); }
     }
 
 
     /// Visit all nodes mutable (not in graph order).
-    pub fn visit_all_mut(&mut self, visitor: &mut impl Visitor) {
-        for node in &mut self.nodes { node.accept_mut(visitor); }
+    pub fn visit_all_mut(&mut self, visitor: &mut impl Visitor<T>) {
+        for node in self.nodes.iter_mut().filter(|node| !node.removed) { node.accept_mut(visitor); }
     }
 
 
     /// Pre-visit.
-    pub fn pre_visit(&self, visitor: &impl Visitor) {
-        let mut handle = self.root.clone();
-        self.pre_visit_internal(visitor, &mut handle);
+    /// Tree visits stop with a GraphError::Cycle when a child links back to one of its ancestors.
+    pub fn pre_visit(&self, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        self.pre_visit_from(&self.root.clone(), None, true, visitor)
     }
-    fn pre_visit_internal(&self, visitor: &impl Visitor, handle: &mut Handle) {
-        if let Some(node) = self.get_node(&handle) {
-            node.accept(visitor);
-            for child in &node.children {
-                let mut handle = Handle::from(*child);
-                self.pre_visit_internal(visitor, &mut handle);
+
+
+    /// Pre-visit starting at any node.
+    /// Depth is relative to the start node (start is depth 0). A max depth of 'None' visits every level.
+    pub fn pre_visit_from(&self, start: &Handle, max_depth: Option<u32>, include_start: bool, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(start, max_depth, include_start);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                self.nodes[index as usize].accept(visitor);
             }
         }
+        walk.finish(&self.nodes)
     }
 
 
     /// Pre-visit mutable.
-    pub fn pre_visit_mut(&mut self, visitor: &mut impl Visitor) {
-        let mut handle = self.root.clone();
-        self.pre_visit_internal_mut(visitor, &mut handle);
+    pub fn pre_visit_mut(&mut self, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        self.pre_visit_from_mut(&self.root.clone(), None, true, visitor)
     }
-    fn pre_visit_internal_mut(&mut self, visitor: &mut impl Visitor, handle: &mut Handle) {
-        let mut children: Vec<u32> = Vec::new();
-        if let Some(node) = self.get_node_mut(&handle) {
-            node.accept_mut(visitor);
-            children = node.children.clone();
-        }
-        for child in &children {
-            let mut handle = Handle::from(*child);
-            self.pre_visit_internal_mut(visitor, &mut handle);
+
+
+    /// Pre-visit mutable starting at any node.
+    /// Depth is relative to the start node (start is depth 0). A max depth of 'None' visits every level.
+    pub fn pre_visit_from_mut(&mut self, start: &Handle, max_depth: Option<u32>, include_start: bool, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(start, max_depth, include_start);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                self.nodes[index as usize].accept_mut(visitor);
+            }
         }
+        walk.finish(&self.nodes)
     }
 
 
     /// Post-visit.
-    pub fn post_visit(&self, visitor: &impl Visitor) {
-        let mut handle = self.root.clone();
-        self.post_visit_internal(visitor, &mut handle);
+    /// Tree visits stop with a GraphError::Cycle when a child links back to one of its ancestors.
+    pub fn post_visit(&self, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        self.post_visit_from(&self.root.clone(), None, true, visitor)
     }
-    fn post_visit_internal(&self, visitor: &impl Visitor, handle: &mut Handle) {
-        if let Some(node) = self.get_node(&handle) {
-            for child in &node.children {
-                let mut handle = Handle::from(*child);
-                self.post_visit_internal(visitor, &mut handle);
+
+
+    /// Post-visit starting at any node.
+    /// Depth is relative to the start node (start is depth 0). A max depth of 'None' visits every level.
+    pub fn post_visit_from(&self, start: &Handle, max_depth: Option<u32>, include_start: bool, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(start, max_depth, include_start);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                self.nodes[index as usize].accept(visitor);
             }
-            node.accept(visitor);
         }
+        walk.finish(&self.nodes)
     }
 
 
     /// Post-visit mutable.
-    pub fn post_visit_mut(&mut self, visitor: &mut impl Visitor) {
-        let mut handle = self.root.clone();
-        self.post_visit_internal_mut(visitor, &mut handle);
+    pub fn post_visit_mut(&mut self, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        self.post_visit_from_mut(&self.root.clone(), None, true, visitor)
     }
-    fn post_visit_internal_mut(&mut self, visitor: &mut impl Visitor, handle: &mut Handle) {
-        let mut children: Vec<u32> = Vec::new();
-        if let Some(node) = self.get_node_mut(&handle) {
-            children = node.children.clone();
+
+
+    /// Post-visit mutable starting at any node.
+    /// Depth is relative to the start node (start is depth 0). A max depth of 'None' visits every level.
+    pub fn post_visit_from_mut(&mut self, start: &Handle, max_depth: Option<u32>, include_start: bool, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(start, max_depth, include_start);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                self.nodes[index as usize].accept_mut(visitor);
+            }
         }
-        for child in &children {
-            let mut handle = Handle::from(*child);
-            self.post_visit_internal_mut(visitor, &mut handle);
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Check that no child below 'start' links back to one of its ancestors.
+    pub fn check_acyclic(&self, start: &Handle) -> Result<(), GraphError> {
+        let mut walk = self.walk(start, None, true);
+        while walk.next(&self.nodes).is_some() {}
+        walk.finish(&self.nodes)
+    }
+
+
+    /**********************************************************
+     * Graph visitors
+     **********************************************************/
+
+    /// Visit all nodes with a view of the graph (not in graph order).
+    pub fn visit_all_graph(&self, visitor: &impl GraphVisitor<T>) {
+        let view = GraphView::new(&self.nodes, self.root_index());
+        for node in self.nodes.iter().filter(|node| !node.removed) { visitor.visit(&view, node); }
+    }
+
+
+    /// Visit all nodes mutable with a view of the rest of the graph (not in graph order).
+    pub fn visit_all_graph_mut(&mut self, visitor: &mut impl GraphVisitor<T>) {
+        for index in 0..self.nodes.len() as u32 {
+            if !self.nodes[index as usize].removed { self.accept_graph_mut(visitor, index); }
+        }
+    }
+
+
+    /// Pre-visit with a view of the graph.
+    pub fn pre_visit_graph(&self, visitor: &impl GraphVisitor<T>) -> Result<(), GraphError> {
+        let view = GraphView::new(&self.nodes, self.root_index());
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                visitor.visit(&view, &self.nodes[index as usize]);
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Pre-visit mutable with a view of the rest of the graph.
+    /// Parents are visited first, so values copied from a parent are already up to date.
+    pub fn pre_visit_graph_mut(&mut self, visitor: &mut impl GraphVisitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                self.accept_graph_mut(visitor, index);
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Post-visit with a view of the graph.
+    pub fn post_visit_graph(&self, visitor: &impl GraphVisitor<T>) -> Result<(), GraphError> {
+        let view = GraphView::new(&self.nodes, self.root_index());
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                visitor.visit(&view, &self.nodes[index as usize]);
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Post-visit mutable with a view of the rest of the graph.
+    /// Children are visited first, so values gathered from children are already up to date.
+    pub fn post_visit_graph_mut(&mut self, visitor: &mut impl GraphVisitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                self.accept_graph_mut(visitor, index);
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Visit one node mutably with a view of every other node.
+    fn accept_graph_mut(&mut self, visitor: &mut impl GraphVisitor<T>, index: u32) {
+        let root = self.root_index();
+        let (view, node) = GraphView::split(&mut self.nodes, index, root);
+        visitor.visit_mut(&view, node);
+    }
+
+
+    /// Root index (out of range when the root handle can't be resolved).
+    pub(crate) fn root_index(&self) -> u32 {
+        self.node_index(&self.root)
+    }
+
+
+    /**********************************************************
+     * Filtered visitors
+     **********************************************************/
+
+    /// Pre-visit only nodes matching 'visit', only entering subtrees of nodes matching 'enter'.
+    /// The two are independent: a node can be skipped while its subtree is still walked, and the other way around.
+    pub fn pre_visit_filtered(&self, mut visit: impl FnMut(&MachNode<T>) -> bool, mut enter: impl FnMut(&MachNode<T>) -> bool, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                let node = &self.nodes[index as usize];
+                if visit(node) { node.accept(visitor); }
+                if !enter(node) { walk.skip_children(); }
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Pre-visit mutable only nodes matching 'visit', only entering subtrees of nodes matching 'enter'.
+    /// 'enter' is checked after the node has been visited.
+    pub fn pre_visit_filtered_mut(&mut self, mut visit: impl FnMut(&MachNode<T>) -> bool, mut enter: impl FnMut(&MachNode<T>) -> bool, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                let node = &mut self.nodes[index as usize];
+                if visit(node) { node.accept_mut(visitor); }
+                if !enter(node) { walk.skip_children(); }
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Post-visit only nodes matching 'visit', only entering subtrees of nodes matching 'enter'.
+    pub fn post_visit_filtered(&self, mut visit: impl FnMut(&MachNode<T>) -> bool, mut enter: impl FnMut(&MachNode<T>) -> bool, visitor: &impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            match step {
+                Step::Enter(index) => {
+                    if !enter(&self.nodes[index as usize]) { walk.skip_children(); }
+                },
+                Step::Leave(index) => {
+                    let node = &self.nodes[index as usize];
+                    if visit(node) { node.accept(visitor); }
+                },
+            }
         }
-        if let Some(node) = self.get_node_mut(&handle) {
-            node.accept_mut(visitor);
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Post-visit mutable only nodes matching 'visit', only entering subtrees of nodes matching 'enter'.
+    /// 'enter' is checked before any of the subtree has been visited.
+    pub fn post_visit_filtered_mut(&mut self, mut visit: impl FnMut(&MachNode<T>) -> bool, mut enter: impl FnMut(&MachNode<T>) -> bool, visitor: &mut impl Visitor<T>) -> Result<(), GraphError> {
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            match step {
+                Step::Enter(index) => {
+                    if !enter(&self.nodes[index as usize]) { walk.skip_children(); }
+                },
+                Step::Leave(index) => {
+                    let node = &mut self.nodes[index as usize];
+                    if visit(node) { node.accept_mut(visitor); }
+                },
+            }
+        }
+        walk.finish(&self.nodes)
+    }
+
+
+    /// Walk for a start handle. Walks nothing when the handle is not found.
+    fn walk(&self, start: &Handle, max_depth: Option<u32>, include_start: bool) -> Walk {
+        let index = start.get_index(self).unwrap_or(u32::MAX);
+        Walk::new(index, max_depth, include_start)
+    }
+
+
+    /**********************************************************
+     * Deferred visitors
+     **********************************************************/
+
+    /// Visit all nodes recording edits, then apply them (not in graph order).
+    pub fn visit_all_deferred(&mut self, visitor: &mut impl CommandVisitor<T>) -> Result<AppliedCommands, GraphError> where T: Default {
+        let mut commands = GraphCommands::new();
+        for node in self.nodes.iter().filter(|node| !node.removed) { visitor.visit(node, &mut commands); }
+        commands.apply(self)
+    }
+
+
+    /// Pre-visit recording edits, then apply them.
+    pub fn pre_visit_deferred(&mut self, visitor: &mut impl CommandVisitor<T>) -> Result<AppliedCommands, GraphError> where T: Default {
+        let mut commands = GraphCommands::new();
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                visitor.visit(&self.nodes[index as usize], &mut commands);
+            }
+        }
+        walk.finish(&self.nodes)?;
+        commands.apply(self)
+    }
+
+
+    /// Post-visit recording edits, then apply them.
+    pub fn post_visit_deferred(&mut self, visitor: &mut impl CommandVisitor<T>) -> Result<AppliedCommands, GraphError> where T: Default {
+        let mut commands = GraphCommands::new();
+        let mut walk = self.walk(&self.root, None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Leave(index) = step {
+                visitor.visit(&self.nodes[index as usize], &mut commands);
+            }
         }
+        walk.finish(&self.nodes)?;
+        commands.apply(self)
     }
 }
\ No newline at end of file
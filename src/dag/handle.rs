@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize};
 use super::{MachGraph, MachNode};
+use super::path::segments;
 
 
 ///
@@ -30,17 +31,17 @@ impl Handle {
 
     ///
     /// Get index.
+    /// None if the node doesn't exist or was removed.
     /// 
-    pub fn get_index(&self, graph: &MachGraph) -> Option<u32> {
-        if self.has_index() { return self.index.clone(); }
-        Self::index(graph, &self.path)
+    pub fn get_index<T>(&self, graph: &MachGraph<T>) -> Option<u32> {
+        graph.get_node(self).map(|node| node.index)
     }
 
 
     ///
     /// Set index.
     /// 
-    pub fn set_index(&mut self, graph: &MachGraph) {
+    pub fn set_index<T>(&mut self, graph: &MachGraph<T>) {
         self.index = Self::index(graph, &self.path);
     }
 
@@ -48,7 +49,7 @@ impl Handle {
     ///
     /// Set path.
     /// 
-    pub fn set_path(&mut self, graph: &MachGraph) {
+    pub fn set_path<T>(&mut self, graph: &MachGraph<T>) {
         if self.has_index() {
             if let Some(path) = Self::path(graph, self.index.unwrap()) {
                 self.path = path;
@@ -60,12 +61,15 @@ impl Handle {
     ///
     /// Get a path for an index.
     /// 
-    pub fn path(graph: &MachGraph, index: u32) -> Option<String> {
+    pub fn path<T>(graph: &MachGraph<T>, index: u32) -> Option<String> {
         let mut current_index = index as usize;
         if current_index < graph.nodes.len() {
             let mut current = &graph.nodes[current_index];
             let mut result = current.name.clone();
+            let mut remaining = graph.nodes.len();
             while current.has_parent() {
+                if remaining == 0 { return None; } // Parents loop back on themselves.
+                remaining -= 1;
                 current_index = current.parent as usize;
                 if current_index < graph.nodes.len() {
                     current = &graph.nodes[current_index];
@@ -83,30 +87,27 @@ impl Handle {
     ///
     /// Get an index for a path.
     /// Does the best it can with the path provided.
+    /// Segments can be '*' for any name and filter on attributes: 'arms.*[side=left]'.
     /// 
-    pub fn index(graph: &MachGraph, path: &String) -> Option<u32> {
-        let mut current: &MachNode = graph.get_root().expect("No root found on graph");
-        let mut set = false;
-        for name in path.split('.') {
-            // Get starting node - first node found with 'name'. Assumes first name in path is unique or starts high enough in the tree.
-            if !set {
-                for node in &graph.nodes {
-                    if node.name == name {
-                        current = node;
-                        set = true;
-                        break;
-                    }
+    pub fn index<T>(graph: &MachGraph<T>, path: &str) -> Option<u32> {
+        let mut current: &MachNode<T> = graph.get_root().expect("No root found on graph");
+        for (position, segment) in segments(path).into_iter().enumerate() {
+            // Get starting node - first node found matching 'segment'. Assumes first segment in path is unique or starts high enough in the tree.
+            if position == 0 {
+                match graph.nodes.iter().find(|node| segment.matches(node) && !node.removed) {
+                    Some(node) => current = node,
+                    None => return None,
                 }
-                if !set { return None; }
+                continue;
             }
             // If name != current.name, then current needs to be updated. Look in currents children.
-            if name != current.name {
+            if !segment.is_name() || !segment.matches(current) {
                 let mut found = false;
                 for child_index in &current.children {
                     let idx = *child_index as usize;
                     if idx < graph.nodes.len() {
                         let child = &graph.nodes[idx];
-                        if child.name == name {
+                        if segment.matches(child) {
                             current = child;
                             found = true;
                             break;
@@ -127,7 +128,7 @@ impl Handle {
 impl From<String> for Handle {
     fn from(path: String) -> Self {
         Self {
-            path: path,
+            path,
             index: None
         }
     }
@@ -166,7 +167,7 @@ impl From<u32> for Handle {
 impl From<(String, u32)> for Handle {
     fn from((path, index): (String, u32)) -> Self {
         Self {
-            path: path,
+            path,
             index: Some(index)
         }
     }
@@ -184,3 +185,16 @@ impl From<(&str, u32)> for Handle {
         }
     }
 }
+
+
+///
+/// From a node (name and index).
+/// 
+impl<T> From<&MachNode<T>> for Handle {
+    fn from(node: &MachNode<T>) -> Self {
+        Self {
+            path: node.name.clone(),
+            index: Some(node.index)
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::fmt;
+use super::{MachNode, ComponentRef};
+
+
+///
+/// Hook called with the node and the component added to or removed from it.
+///
+pub type ComponentHook<T = ()> = Box<dyn FnMut(&MachNode<T>, ComponentRef) + Send + Sync>;
+
+
+///
+/// ComponentHooks.
+/// Callbacks per component type, run when MachGraph adds or removes components of that type.
+/// Lets caches that depend on components stay in sync with the graph.
+///
+pub struct ComponentHooks<T = ()> {
+    on_add: HashMap<u32, Vec<ComponentHook<T>>>,
+    on_remove: HashMap<u32, Vec<ComponentHook<T>>>,
+}
+
+
+///
+/// Default implementation.
+///
+impl<T> Default for ComponentHooks<T> {
+    fn default() -> Self {
+        Self {
+            on_add: HashMap::new(),
+            on_remove: HashMap::new(),
+        }
+    }
+}
+
+
+///
+/// Debug implementation (number of hooks per component type).
+///
+impl<T> fmt::Debug for ComponentHooks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |hooks: &HashMap<u32, Vec<ComponentHook<T>>>| -> HashMap<u32, usize> {
+            hooks.iter().map(|(kind, hooks)| (*kind, hooks.len())).collect()
+        };
+        f.debug_struct("ComponentHooks")
+            .field("on_add", &count(&self.on_add))
+            .field("on_remove", &count(&self.on_remove))
+            .finish()
+    }
+}
+
+
+///
+/// Implementation for ComponentHooks.
+///
+impl<T> ComponentHooks<T> {
+    /// Register a hook for components of a type being added.
+    pub fn on_add(&mut self, kind: u32, hook: ComponentHook<T>) {
+        self.on_add.entry(kind).or_default().push(hook);
+    }
+
+
+    /// Register a hook for components of a type being removed.
+    pub fn on_remove(&mut self, kind: u32, hook: ComponentHook<T>) {
+        self.on_remove.entry(kind).or_default().push(hook);
+    }
+
+
+    /// Drop all hooks of a component type.
+    pub fn clear(&mut self, kind: u32) {
+        self.on_add.remove(&kind);
+        self.on_remove.remove(&kind);
+    }
+
+
+    /// Run the add hooks for a component.
+    pub(crate) fn added(&mut self, node: &MachNode<T>, component: ComponentRef) {
+        for hook in self.on_add.get_mut(&component.kind).into_iter().flatten() {
+            hook(node, component);
+        }
+    }
+
+
+    /// Run the remove hooks for a component.
+    pub(crate) fn removed(&mut self, node: &MachNode<T>, component: ComponentRef) {
+        for hook in self.on_remove.get_mut(&component.kind).into_iter().flatten() {
+            hook(node, component);
+        }
+    }
+}
\ No newline at end of file
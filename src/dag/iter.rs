@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use super::MachNode;
+
+
+///
+/// Ancestors iterator.
+/// Walks parent links from a node up to the root (node itself not included).
+///
+pub struct Ancestors<'a, T = ()> {
+    nodes: &'a [MachNode<T>],
+    current: Option<u32>,
+    remaining: usize,
+}
+
+
+///
+/// Ancestors implementation.
+/// 
+impl<'a, T> Ancestors<'a, T> {
+    /// New ancestors iterator for a node index.
+    pub fn new(nodes: &'a [MachNode<T>], index: u32) -> Self {
+        Self {
+            nodes,
+            current: Some(index),
+            remaining: nodes.len(),
+        }
+    }
+}
+
+
+///
+/// Iterator implementation.
+/// Bounded by the node count so a broken parent chain can't loop forever.
+/// 
+impl<'a, T> Iterator for Ancestors<'a, T> {
+    type Item = &'a MachNode<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.nodes.get(self.current? as usize)?;
+        if !node.has_parent() || self.remaining == 0 {
+            self.current = None;
+            return None;
+        }
+        self.remaining -= 1;
+        let parent = self.nodes.get(node.parent as usize);
+        self.current = parent.map(|parent| parent.index);
+        parent
+    }
+}
+
+
+///
+/// Descendants iterator.
+/// Pre-order walk below a node (node itself not included), using a stack instead of recursion.
+/// Removed nodes are skipped. Every node is yielded once, so shared children and cycles can't repeat or loop.
+///
+pub struct Descendants<'a, T = ()> {
+    nodes: &'a [MachNode<T>],
+    stack: Vec<u32>,
+    seen: HashSet<u32>,
+}
+
+
+///
+/// Descendants implementation.
+/// 
+impl<'a, T> Descendants<'a, T> {
+    /// New descendants iterator for a node index.
+    pub fn new(nodes: &'a [MachNode<T>], index: u32) -> Self {
+        let mut descendants = Self::inclusive(nodes, index);
+        descendants.next();
+        descendants
+    }
+
+
+    /// New iterator that also yields the start node first.
+    pub fn inclusive(nodes: &'a [MachNode<T>], index: u32) -> Self {
+        let mut stack = Vec::new();
+        if (index as usize) < nodes.len() { stack.push(index); }
+        Self { nodes, stack, seen: HashSet::new() }
+    }
+
+
+    /// Push children in reverse so the first child pops first.
+    fn push_children(&mut self, node: &MachNode<T>) {
+        self.stack.extend(node.children.iter().rev());
+    }
+}
+
+
+///
+/// Iterator implementation.
+/// 
+impl<'a, T> Iterator for Descendants<'a, T> {
+    type Item = &'a MachNode<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(index) = self.stack.pop() {
+            if let Some(node) = self.nodes.get(index as usize).filter(|node| !node.removed) {
+                if !self.seen.insert(index) { continue; }
+                self.push_children(node);
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+
+///
+/// Siblings iterator.
+/// Other live children of a node's parent, in child order.
+///
+pub struct Siblings<'a, T = ()> {
+    nodes: &'a [MachNode<T>],
+    children: std::slice::Iter<'a, u32>,
+    index: u32,
+}
+
+
+///
+/// Siblings implementation.
+/// 
+impl<'a, T> Siblings<'a, T> {
+    /// New siblings iterator for a node index. A node without a parent has no siblings.
+    pub fn new(nodes: &'a [MachNode<T>], index: u32) -> Self {
+        let children = nodes.get(index as usize)
+            .filter(|node| node.has_parent())
+            .and_then(|node| nodes.get(node.parent as usize))
+            .map(|parent| parent.children.iter())
+            .unwrap_or_default();
+        Self { nodes, children, index }
+    }
+}
+
+
+///
+/// Iterator implementation.
+/// 
+impl<'a, T> Iterator for Siblings<'a, T> {
+    type Item = &'a MachNode<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for child in self.children.by_ref() {
+            if *child == self.index { continue; }
+            if let Some(node) = self.nodes.get(*child as usize).filter(|node| !node.removed) {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+
+///
+/// Leaves iterator.
+/// Nodes without live children below (and including) a node, in pre-order.
+///
+pub struct Leaves<'a, T = ()> {
+    descendants: Descendants<'a, T>,
+}
+
+
+///
+/// Leaves implementation.
+/// 
+impl<'a, T> Leaves<'a, T> {
+    /// New leaves iterator for a node index.
+    pub fn new(nodes: &'a [MachNode<T>], index: u32) -> Self {
+        Self { descendants: Descendants::inclusive(nodes, index) }
+    }
+}
+
+
+///
+/// Iterator implementation.
+/// 
+impl<'a, T> Iterator for Leaves<'a, T> {
+    type Item = &'a MachNode<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = self.descendants.nodes;
+        self.descendants.by_ref().find(|node| {
+            node.children.iter().all(|child| nodes.get(*child as usize).is_none_or(|child| child.removed))
+        })
+    }
+}
\ No newline at end of file
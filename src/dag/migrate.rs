@@ -0,0 +1,105 @@
+use serde_json::{Map, Value};
+use super::{GraphError, FORMAT_VERSION};
+
+
+/// Upgrades a document by one format version.
+type Migration = fn(Value) -> Result<Value, GraphError>;
+
+
+/// Migrations in order: MIGRATIONS[n] upgrades a version n document to version n + 1.
+/// To change the format, add a migration here and bump FORMAT_VERSION.
+const MIGRATIONS: [Migration; 1] = [
+    v0_to_v1,
+];
+
+const _: () = assert!(MIGRATIONS.len() as u32 == FORMAT_VERSION, "every format version needs a migration");
+
+
+/// Format version of a document. Documents without one are version 0.
+fn version(document: &Value) -> Result<u32, GraphError> {
+    match document.get("version") {
+        Some(version) => version.as_u64().and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| GraphError::format(format!("bad format version {}", version))),
+        None => Ok(0),
+    }
+}
+
+
+/// Upgrade a document to the current format version.
+/// Documents from a newer release are an error naming their version.
+pub(crate) fn migrate(mut document: Value) -> Result<Value, GraphError> {
+    let version = version(&document)?;
+    if version > FORMAT_VERSION { return Err(GraphError::UnsupportedVersion(version)); }
+    for migration in &MIGRATIONS[version as usize..] {
+        document = migration(document)?;
+    }
+    if let Some(document) = document.as_object_mut() {
+        document.insert(String::from("version"), Value::from(FORMAT_VERSION));
+    }
+    Ok(document)
+}
+
+
+/// Version 0 is the derived serde shape of MachGraph, before the file format was versioned:
+/// the root is a handle and nodes repeat their parent and index, with removed nodes flagged.
+fn v0_to_v1(mut document: Value) -> Result<Value, GraphError> {
+    graph_v0_to_v1(&mut document)?;
+    Ok(document)
+}
+
+
+/// Upgrade a version 0 graph (top level or prototype).
+fn graph_v0_to_v1(graph: &mut Value) -> Result<(), GraphError> {
+    let graph = graph.as_object_mut().ok_or_else(|| GraphError::format("version 0 file: graph is not an object"))?;
+    let root = match graph.get("root") {
+        Some(Value::Object(handle)) => handle.get("index").and_then(Value::as_u64),
+        Some(root) => root.as_u64(),
+        None => None,
+    };
+    let Some(root) = root else { return Err(GraphError::format("version 0 file: root has no index")); };
+    graph.insert(String::from("root"), Value::from(root));
+
+    if let Some(Value::Array(nodes)) = graph.get_mut("nodes") {
+        for node in nodes.iter_mut() {
+            if node.get("removed").and_then(Value::as_bool) == Some(true) { *node = Value::Null; }
+        }
+        let listed = listed(nodes);
+        for (index, node) in nodes.iter_mut().enumerate() {
+            let Value::Object(fields) = node else { continue; };
+            node_v0_to_v1(fields, listed[index].unwrap_or(index as u64));
+        }
+    }
+    if let Some(Value::Array(prototypes)) = graph.get_mut("prototypes") {
+        for prototype in prototypes.iter_mut() {
+            if let Some(graph) = prototype.get_mut("graph") { graph_v0_to_v1(graph)?; }
+        }
+    }
+    Ok(())
+}
+
+
+/// First node listing each node as a child, by index.
+fn listed(nodes: &[Value]) -> Vec<Option<u64>> {
+    let mut listed = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        let Some(children) = node.get("children").and_then(Value::as_array) else { continue; };
+        for child in children.iter().filter_map(Value::as_u64) {
+            if let Some(first) = listed.get_mut(child as usize) { first.get_or_insert(index as u64); }
+        }
+    }
+    listed
+}
+
+
+/// Upgrade a version 0 node: drop what the children lists already say (the parent is kept when it isn't 'listed'), and empty lists.
+fn node_v0_to_v1(node: &mut Map<String, Value>, listed: u64) {
+    if node.get("parent").and_then(Value::as_u64) == Some(listed) { node.remove("parent"); }
+    for field in ["index", "removed"] {
+        node.remove(field);
+    }
+    for field in ["children", "components"] {
+        if node.get(field).and_then(Value::as_array).is_some_and(Vec::is_empty) {
+            node.remove(field);
+        }
+    }
+}
\ No newline at end of file
@@ -7,11 +7,65 @@ pub use node::*;
 pub mod handle;
 pub use handle::*;
 
+pub mod error;
+pub use error::*;
+
+pub mod attribute;
+pub use attribute::*;
+
+pub mod tags;
+pub use tags::*;
+
+pub mod edges;
+pub use edges::*;
+
 pub mod visitor;
 pub use visitor::*;
 
+pub mod combinators;
+pub use combinators::*;
+
+pub mod iter;
+pub use iter::*;
+
+pub mod view;
+pub use view::*;
+
+pub mod commands;
+pub use commands::*;
+
+pub mod store;
+pub use store::*;
+
+pub mod query;
+pub use query::*;
+
+pub mod hooks;
+pub use hooks::*;
+
+pub mod prototype;
+pub use prototype::*;
+
+pub mod format;
+pub use format::*;
+
+pub mod binary;
+pub use binary::*;
+
+mod walk;
+
+mod path;
+
+mod migrate;
+
+mod refs;
+
+pub mod topology;
+
+#[cfg(feature = "parallel")]
+pub mod parallel;
+
 #[macro_use]
 pub mod graph_macro;
-pub use graph_macro::*;
 
 mod test;
\ No newline at end of file
@@ -1,14 +1,16 @@
-use serde::{Deserialize, Serialize};
-use crate::dag::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+use crate::dag::{Visitor, Attribute, Attributes};
 
 
 ///
 /// MachNode.
 /// This is where data is referenced/owned as relational to other data.
 /// Nodes are meant to be as small as possible. They are used for relationships, not actual data.
+/// Nodes can carry a small payload 'T' directly (none by default).
 ///
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MachNode {
+pub struct MachNode<T = ()> {
     /// Name of this node - does not have to be unique to the graph.
     pub name: String,
 
@@ -21,23 +23,35 @@ pub struct MachNode {
     /// Children of this node.
     pub children: Vec<u32>,
 
-    /// Index of components in data store (not owned by graph).
-    pub components: Vec<u32>,
+    /// Components in data stores (not owned by graph), tagged with their component type.
+    pub components: Vec<ComponentRef>,
+
+    /// Attributes - small typed metadata (None until the first one is set, to keep nodes small).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Box<Attributes>>,
+
+    /// Removed from the graph. The slot is kept so indices of other nodes stay valid.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+
+    /// Payload of this node (not serialized when zero sized, like '()').
+    #[serde(default, skip_serializing_if = "is_zero_sized")]
+    pub data: T,
+}
+
+
+/// Zero sized payloads carry no information.
+pub(crate) fn is_zero_sized<T>(_: &T) -> bool {
+    std::mem::size_of::<T>() == 0
 }
 
 
 ///
 /// Default implementation.
 /// 
-impl Default for MachNode {
+impl<T: Default> Default for MachNode<T> {
     fn default() -> Self {
-        Self {
-            name: String::from("root"),
-            parent: 0,
-            index: 0,
-            children: Vec::new(),
-            components: Vec::new()
-        }
+        Self::with_data(String::from("root"), T::default())
     }
 }
 
@@ -45,16 +59,31 @@ impl Default for MachNode {
 ///
 /// Implementation for MachNode.
 /// 
-impl MachNode {
+impl<T> MachNode<T> {
     /// New mach node with name.
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String) -> Self where T: Default {
         Self {
-            name: name,
+            name,
             ..Default::default()
         }
     }
 
 
+    /// New mach node with name and payload.
+    pub fn with_data(name: String, data: T) -> Self {
+        Self {
+            name,
+            parent: 0,
+            index: 0,
+            children: Vec::new(),
+            components: Vec::new(),
+            attributes: None,
+            removed: false,
+            data,
+        }
+    }
+
+
     /// Has parent?
     pub fn has_parent(&self) -> bool {
         self.index != self.parent
@@ -63,13 +92,58 @@ impl MachNode {
 
     /// Has children?
     pub fn has_children(&self) -> bool {
-        self.children.len() > 0
+        !self.children.is_empty()
     }
 
 
     /// Has components?
     pub fn has_components(&self) -> bool {
-        self.components.len() > 0
+        !self.components.is_empty()
+    }
+
+
+    /// Has a component of a type?
+    pub fn has_component(&self, kind: u32) -> bool {
+        self.components.iter().any(|component| component.kind == kind)
+    }
+
+
+    /// Indices of the components of a type, in order.
+    pub fn components_of_type(&self, kind: u32) -> impl Iterator<Item = u32> + '_ {
+        self.components.iter()
+            .filter(move |component| component.kind == kind)
+            .map(|component| component.index)
+    }
+
+
+    /**********************************************************
+     * Attributes
+     **********************************************************/
+
+    /// Get an attribute.
+    pub fn attribute(&self, key: &str) -> Option<&Attribute> {
+        self.attributes.as_ref()?.get(key)
+    }
+
+
+    /// Set an attribute, returning the value it replaces.
+    pub fn set_attribute(&mut self, key: &str, value: impl Into<Attribute>) -> Option<Attribute> {
+        self.attributes.get_or_insert_with(Default::default).insert(String::from(key), value.into())
+    }
+
+
+    /// Remove an attribute.
+    pub fn remove_attribute(&mut self, key: &str) -> Option<Attribute> {
+        let attributes = self.attributes.as_mut()?;
+        let removed = attributes.remove(key);
+        if attributes.is_empty() { self.attributes = None; }
+        removed
+    }
+
+
+    /// Iterate attributes in key order.
+    pub fn attributes(&self) -> impl Iterator<Item = (&String, &Attribute)> {
+        self.attributes.iter().flat_map(|attributes| attributes.iter())
     }
 
 
@@ -78,13 +152,13 @@ impl MachNode {
      **********************************************************/
 
     /// Accept a visitor.
-    pub fn accept(&self, visitor: &impl Visitor) {
+    pub fn accept(&self, visitor: &impl Visitor<T>) {
         visitor.visit(self);
     }
 
 
     /// Accept a visitor mutable.
-    pub fn accept_mut(&mut self, visitor: &mut impl Visitor) {
+    pub fn accept_mut(&mut self, visitor: &mut impl Visitor<T>) {
         visitor.visit_mut(self);
     }
 }
@@ -93,12 +167,103 @@ impl MachNode {
 ///
 /// From a parent.
 /// 
-impl From<(String, u32)> for MachNode {
+impl<T: Default> From<(String, u32)> for MachNode<T> {
     fn from((name, parent): (String, u32)) -> Self {
         Self {
-            name: name,
-            parent: parent,
+            name,
+            parent,
             ..Default::default()
         }
     }
 }
+
+
+
+///
+/// ComponentRef.
+/// Reference to a component in a data store: the component type plus the index in that type's store.
+/// Indices of different types are unrelated, so the type is needed to tell them apart.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentRef {
+    /// Component type identifier (chosen by the application, 0 is untyped).
+    pub kind: u32,
+
+    /// Index of the component in the store of its type.
+    pub index: u32,
+}
+
+
+///
+/// Implementation for ComponentRef.
+/// 
+impl ComponentRef {
+    /// Component type of references without one (plain indices).
+    pub const UNTYPED: u32 = 0;
+
+
+    /// New component reference.
+    pub fn new(kind: u32, index: u32) -> Self {
+        Self { kind, index }
+    }
+
+
+    /// Has a component type?
+    pub fn is_typed(&self) -> bool {
+        self.kind != Self::UNTYPED
+    }
+}
+
+
+///
+/// From a plain (untyped) index.
+/// 
+impl From<u32> for ComponentRef {
+    fn from(index: u32) -> Self {
+        Self::new(Self::UNTYPED, index)
+    }
+}
+
+
+///
+/// From a (type, index) pair.
+/// 
+impl From<(u32, u32)> for ComponentRef {
+    fn from((kind, index): (u32, u32)) -> Self {
+        Self::new(kind, index)
+    }
+}
+
+
+///
+/// Serialize implementation.
+/// Untyped references are written as plain indices, so graphs without component types keep their old format.
+/// 
+impl Serialize for ComponentRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.is_typed() { return serializer.serialize_u32(self.index); }
+        let mut state = serializer.serialize_struct("ComponentRef", 2)?;
+        state.serialize_field("kind", &self.kind)?;
+        state.serialize_field("index", &self.index)?;
+        state.end()
+    }
+}
+
+
+///
+/// Deserialize implementation (accepts plain indices from older files).
+/// 
+impl<'de> Deserialize<'de> for ComponentRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Index(u32),
+            Typed { kind: u32, index: u32 },
+        }
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Index(index) => ComponentRef::from(index),
+            Repr::Typed { kind, index } => ComponentRef::new(kind, index),
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+use rayon::prelude::*;
+use crate::dag::Visitor;
+use super::{MachGraph, MachNode, Handle, GraphError};
+use super::walk::{Walk, Step};
+
+
+/// Depth below which subtrees are finished sequentially, so deep graphs don't overflow the worker stacks.
+const PAR_DEPTH: u32 = 32;
+
+
+///
+/// Parallel implementation for MachGraph (feature "parallel").
+/// Sibling subtrees are independent, so they are handed to the rayon thread pool.
+/// Visit order is not deterministic, results from the map functions are.
+/// Subtree traversals first walk the subtree sequentially, failing with GraphError::Cycle on a cycle. That walk decides
+/// which parent a shared child is visited under (the first one that reaches it), so every node is visited once.
+/// Only the top PAR_DEPTH levels are split across threads; deeper subtrees are walked without recursion.
+///
+impl<T: Sync> MachGraph<T> {
+    /// Visit all nodes in parallel (not in graph order).
+    pub fn par_visit_all(&self, visitor: &(impl Visitor<T> + Sync)) {
+        self.nodes.par_iter().filter(|node| !node.removed).for_each(|node| node.accept(visitor));
+    }
+
+
+    /// Map all nodes in parallel. Results are indexed by node index - removed nodes are 'None'.
+    pub fn par_map_all<R: Send>(&self, f: impl Fn(&MachNode<T>) -> R + Sync) -> Vec<Option<R>> {
+        self.nodes.par_iter().map(|node| (!node.removed).then(|| f(node))).collect()
+    }
+
+
+    /// Pre-visit in parallel. A node is always visited before its children.
+    pub fn par_pre_visit(&self, visitor: &(impl Visitor<T> + Sync)) -> Result<(), GraphError> {
+        self.par_pre_visit_from(&self.root.clone(), visitor)
+    }
+
+
+    /// Pre-visit in parallel starting at any node.
+    pub fn par_pre_visit_from(&self, start: &Handle, visitor: &(impl Visitor<T> + Sync)) -> Result<(), GraphError> {
+        let owners = self.owners(start)?;
+        if let Some(index) = start.get_index(self) {
+            self.par_pre_visit_internal(visitor, &owners, index, 0);
+        }
+        Ok(())
+    }
+    fn par_pre_visit_internal(&self, visitor: &(impl Visitor<T> + Sync), owners: &[u32], index: u32, depth: u32) {
+        if depth == PAR_DEPTH {
+            return self.walk_subtree(owners, index, |step, node| if let Step::Enter(_) = step { node.accept(visitor) });
+        }
+        let node = &self.nodes[index as usize];
+        node.accept(visitor);
+        self.owned_children(owners, index).for_each(|child| self.par_pre_visit_internal(visitor, owners, child, depth + 1));
+    }
+
+
+    /// Post-visit in parallel. A node is visited after all of its children, except shared children visited under another parent.
+    pub fn par_post_visit(&self, visitor: &(impl Visitor<T> + Sync)) -> Result<(), GraphError> {
+        self.par_post_visit_from(&self.root.clone(), visitor)
+    }
+
+
+    /// Post-visit in parallel starting at any node.
+    pub fn par_post_visit_from(&self, start: &Handle, visitor: &(impl Visitor<T> + Sync)) -> Result<(), GraphError> {
+        let owners = self.owners(start)?;
+        if let Some(index) = start.get_index(self) {
+            self.par_post_visit_internal(visitor, &owners, index, 0);
+        }
+        Ok(())
+    }
+    fn par_post_visit_internal(&self, visitor: &(impl Visitor<T> + Sync), owners: &[u32], index: u32, depth: u32) {
+        if depth == PAR_DEPTH {
+            return self.walk_subtree(owners, index, |step, node| if let Step::Leave(_) = step { node.accept(visitor) });
+        }
+        self.owned_children(owners, index).for_each(|child| self.par_post_visit_internal(visitor, owners, child, depth + 1));
+        self.nodes[index as usize].accept(visitor);
+    }
+
+
+    /// Map a subtree in parallel. Results are in pre-order, same as a sequential pre-visit.
+    pub fn par_map_subtree<R: Send>(&self, start: &Handle, f: impl Fn(&MachNode<T>) -> R + Sync) -> Result<Vec<R>, GraphError> {
+        let owners = self.owners(start)?;
+        match start.get_index(self) {
+            Some(index) => Ok(self.par_map_subtree_internal(&f, &owners, index, 0)),
+            None => Ok(Vec::new()),
+        }
+    }
+    fn par_map_subtree_internal<R: Send>(&self, f: &(impl Fn(&MachNode<T>) -> R + Sync), owners: &[u32], index: u32, depth: u32) -> Vec<R> {
+        let mut results = Vec::new();
+        if depth == PAR_DEPTH {
+            self.walk_subtree(owners, index, |step, node| if let Step::Enter(_) = step { results.push(f(node)) });
+            return results;
+        }
+        results.push(f(&self.nodes[index as usize]));
+        let subtrees: Vec<Vec<R>> = self.owned_children(owners, index)
+            .map(|child| self.par_map_subtree_internal(f, owners, child, depth + 1))
+            .collect();
+        for subtree in subtrees { results.extend(subtree); }
+        results
+    }
+
+
+    /// Parent each node of a subtree is walked under (u32::MAX for nodes outside of it), from a sequential walk.
+    fn owners(&self, start: &Handle) -> Result<Vec<u32>, GraphError> {
+        let mut owners = vec![u32::MAX; self.nodes.len()];
+        let mut walk = Walk::new(start.get_index(self).unwrap_or(u32::MAX), None, true);
+        while let Some(step) = walk.next(&self.nodes) {
+            if let Step::Enter(index) = step {
+                owners[index as usize] = walk.parent().unwrap_or(index);
+            }
+        }
+        walk.finish(&self.nodes)?;
+        Ok(owners)
+    }
+
+
+    /// Children a node is the walk parent of, in parallel.
+    fn owned_children<'a>(&'a self, owners: &'a [u32], index: u32) -> impl ParallelIterator<Item = u32> + 'a {
+        self.nodes[index as usize].children.par_iter()
+            .filter(move |child| **child != index && owners.get(**child as usize) == Some(&index))
+            .copied()
+    }
+
+
+    /// Walk a subtree sequentially, without recursion, entering only the children a node is the walk parent of.
+    fn walk_subtree(&self, owners: &[u32], index: u32, mut step: impl FnMut(Step, &MachNode<T>)) {
+        let mut stack = vec![(index, 0)];
+        step(Step::Enter(index), &self.nodes[index as usize]);
+        while let Some((index, next)) = stack.last_mut() {
+            let node = &self.nodes[*index as usize];
+            let owned = node.children[*next..].iter()
+                .position(|child| *child != node.index && owners.get(*child as usize) == Some(&node.index));
+            match owned {
+                Some(position) => {
+                    let child = node.children[*next + position];
+                    *next += position + 1;
+                    stack.push((child, 0));
+                    step(Step::Enter(child), &self.nodes[child as usize]);
+                },
+                None => {
+                    stack.pop();
+                    step(Step::Leave(node.index), node);
+                },
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,90 @@
+use super::{MachNode, Attributes};
+
+
+///
+/// Path segment.
+/// One dot-separated part of a path: a node name ('*' or nothing for any) followed by optional
+/// attribute predicates, e.g. 'button[color=red][visible]' or '[visible]'.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Segment<'a> {
+    /// Node name, or None for any.
+    pub name: Option<&'a str>,
+
+    /// Attribute predicates: key, and the value to match if any.
+    pub predicates: Vec<(&'a str, Option<&'a str>)>,
+}
+
+
+///
+/// Implementation for Segment.
+///
+impl<'a> Segment<'a> {
+    /// Parse a segment.
+    pub fn parse(text: &'a str) -> Self {
+        let (name, mut rest) = match text.find('[') {
+            Some(open) => (&text[..open], &text[open..]),
+            None => (text, ""),
+        };
+        let mut predicates = Vec::new();
+        while let Some(open) = rest.find('[') {
+            let close = rest[open..].find(']').map_or(rest.len(), |close| open + close);
+            let predicate = &rest[open + 1..close];
+            predicates.push(match predicate.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (predicate.trim(), None),
+            });
+            rest = rest.get(close + 1..).unwrap_or("");
+        }
+        let any = name == "*" || (name.is_empty() && !predicates.is_empty());
+        Self { name: if any { None } else { Some(name) }, predicates }
+    }
+
+
+    /// Is this a plain name (no wildcard or predicates)?
+    pub fn is_name(&self) -> bool {
+        self.name.is_some() && self.predicates.is_empty()
+    }
+
+
+    /// Does a node match this segment?
+    pub fn matches<T>(&self, node: &MachNode<T>) -> bool {
+        self.matches_with(&node.name, node.attributes.as_deref())
+    }
+
+
+    /// Does a node with this name and these attributes match this segment?
+    pub fn matches_with(&self, name: &str, attributes: Option<&Attributes>) -> bool {
+        if let Some(own) = self.name {
+            if own != name { return false; }
+        }
+        self.predicates.iter().all(|(key, value)| match (attributes.and_then(|attributes| attributes.get(*key)), value) {
+            (Some(attribute), Some(value)) => attribute.matches(value),
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+    }
+}
+
+
+///
+/// Split a path into segments on the dots outside of predicates.
+///
+pub(crate) fn segments(path: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (position, c) in path.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '.' if depth == 0 => {
+                segments.push(Segment::parse(&path[start..position]));
+                start = position + 1;
+            },
+            _ => {},
+        }
+    }
+    segments.push(Segment::parse(&path[start..]));
+    segments
+}
\ No newline at end of file
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use serde::{Serialize, Deserialize};
+use super::{MachGraph, MachNode, Handle, ComponentRef, Attributes, Descendants};
+
+
+///
+/// Prototype.
+/// A reusable subtree (prefab) kept as its own graph, which can be instantiated under any node.
+/// Edit it with MachGraph::edit_prototype so its instances follow.
+///
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Prototype {
+    /// The prototype subtree (its root is the root of every instance).
+    pub graph: MachGraph,
+
+    /// State of each prototype node as instances last saw it (None for removed nodes).
+    /// Instance nodes that still match it aren't overridden.
+    pub(crate) synced: Vec<Option<Synced>>,
+}
+
+
+///
+/// Node state shared with instances.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Synced {
+    pub(crate) name: String,
+    pub(crate) components: Vec<ComponentRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) attributes: Option<Box<Attributes>>,
+}
+
+
+///
+/// From a node.
+///
+impl<T> From<&MachNode<T>> for Synced {
+    fn from(node: &MachNode<T>) -> Self {
+        Self { name: node.name.clone(), components: node.components.clone(), attributes: node.attributes.clone() }
+    }
+}
+
+
+///
+/// Implementation for Prototype.
+///
+impl Prototype {
+    /// New prototype from a graph.
+    fn new(graph: MachGraph) -> Self {
+        let synced = Self::snapshot(&graph);
+        Self { graph, synced }
+    }
+
+
+    /// Current state of every prototype node.
+    fn snapshot(graph: &MachGraph) -> Vec<Option<Synced>> {
+        graph.nodes.iter()
+            .map(|node| if node.removed { None } else { Some(Synced::from(node)) })
+            .collect()
+    }
+
+
+    /// Live prototype nodes in pre-order, so parents come before their children.
+    fn pre_order(&self) -> Vec<u32> {
+        let root = self.graph.get_root().map_or(u32::MAX, |root| root.index);
+        Descendants::inclusive(&self.graph.nodes, root).map(|node| node.index).collect()
+    }
+}
+
+
+///
+/// Instance.
+/// A subtree of the graph created from a prototype.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instance {
+    /// Prototype this is an instance of.
+    pub prototype: u32,
+
+    /// Graph index of the instance root.
+    pub root: u32,
+
+    /// Graph index of the node for each prototype node (u32::MAX when there is none).
+    pub nodes: Vec<u32>,
+}
+
+
+///
+/// Instance nodes.
+/// Lookup from graph nodes to the instance they belong to and the prototype node they follow.
+/// Built from the instances on first use (so deserialized graphs find their instances) and kept up to date
+/// by instantiate, unpack, prototype edits and remove afterwards.
+///
+#[derive(Debug, Default)]
+pub(crate) struct InstanceNodes {
+    nodes: OnceLock<HashMap<u32, InstanceNode>>,
+}
+
+
+///
+/// Instance node entry.
+///
+#[derive(Debug, Clone, Copy)]
+struct InstanceNode {
+    /// Prototype id.
+    prototype: u32,
+
+    /// Graph index of the instance root.
+    root: u32,
+
+    /// Prototype node index.
+    node: u32,
+}
+
+
+///
+/// Implementation for InstanceNodes.
+///
+impl InstanceNodes {
+    /// Entry of a graph node, building the lookup from the instances if needed.
+    fn get(&self, instances: &[Instance], index: u32) -> Option<InstanceNode> {
+        self.nodes.get_or_init(|| {
+            let mut nodes = HashMap::new();
+            for instance in instances { Self::insert(&mut nodes, instance); }
+            nodes
+        }).get(&index).copied()
+    }
+
+
+    /// Add (or update) the nodes of an instance, if the lookup is built.
+    fn add(&mut self, instance: &Instance) {
+        if let Some(nodes) = self.nodes.get_mut() { Self::insert(nodes, instance); }
+    }
+
+
+    /// Drop the nodes of an instance, if the lookup is built.
+    fn remove(&mut self, instance: &Instance) {
+        for index in &instance.nodes { self.release(*index); }
+    }
+
+
+    /// Drop a graph node, if the lookup is built.
+    pub(crate) fn release(&mut self, index: u32) {
+        if let Some(nodes) = self.nodes.get_mut() { nodes.remove(&index); }
+    }
+
+
+    /// Insert the nodes of an instance into a lookup.
+    fn insert(nodes: &mut HashMap<u32, InstanceNode>, instance: &Instance) {
+        for (node, index) in instance.nodes.iter().enumerate() {
+            if *index == u32::MAX { continue; }
+            nodes.insert(*index, InstanceNode { prototype: instance.prototype, root: instance.root, node: node as u32 });
+        }
+    }
+}
+
+
+///
+/// Prototype implementation for MachGraph.
+/// Prototypes have no payload; instance nodes are created with the default payload.
+/// Instance nodes share the names, components and attributes of their prototype nodes until they are changed
+/// (overridden). Edits to a prototype propagate to every instance node that isn't overridden;
+/// prototype nodes added or removed are added to or removed from every instance.
+///
+impl<T: Default> MachGraph<T> {
+    /// Create an empty prototype with a root node name. Returns the prototype id.
+    pub fn create_prototype(&mut self, name: &str) -> u32 {
+        let mut graph = MachGraph::new(name);
+        if let Some(root) = graph.get_root_mut() { root.name = String::from(name); }
+        graph.root = Handle::from((String::from(name), 0));
+        self.prototypes.push(Prototype::new(graph));
+        self.prototypes.len() as u32 - 1
+    }
+
+
+    /// Create a prototype from a copy of a node's subtree. Returns the prototype id.
+    pub fn prototype_from(&mut self, handle: &Handle) -> Option<u32> {
+        let start = self.get_node(handle)?.index;
+        let id = self.create_prototype(&self.nodes[start as usize].name.clone());
+        let mut mapped = vec![u32::MAX; self.nodes.len()];
+        let prototype = &mut self.prototypes[id as usize].graph;
+        for node in Descendants::inclusive(&self.nodes, start) {
+            let index = if node.index == start { 0 } else {
+                let parent = Handle::from(mapped[node.parent as usize]);
+                prototype.push_child_of(&node.name, &parent).index.unwrap_or(u32::MAX)
+            };
+            mapped[node.index as usize] = index;
+            prototype.nodes[index as usize].components = node.components.clone();
+            prototype.nodes[index as usize].attributes = node.attributes.clone();
+        }
+        let prototype = &mut self.prototypes[id as usize];
+        prototype.synced = Prototype::snapshot(&prototype.graph);
+        Some(id)
+    }
+
+
+    /// Prototype graph.
+    pub fn prototype(&self, id: u32) -> Option<&MachGraph> {
+        self.prototypes.get(id as usize).map(|prototype| &prototype.graph)
+    }
+
+
+    /// Edit a prototype, then propagate the changes to its instances.
+    pub fn edit_prototype<R>(&mut self, id: u32, edit: impl FnOnce(&mut MachGraph) -> R) -> Option<R> {
+        let result = edit(&mut self.prototypes.get_mut(id as usize)?.graph);
+        self.sync_prototype(id);
+        Some(result)
+    }
+
+
+    /// Create an instance of a prototype under a parent. Returns the instance root.
+    pub fn instantiate(&mut self, id: u32, parent: &Handle) -> Option<Handle> {
+        let parent = self.get_node(parent)?.index;
+        let prototype = self.prototypes.get(id as usize)?;
+        let nodes = vec![u32::MAX; prototype.graph.nodes.len()];
+        let root = prototype.graph.get_root()?.index;
+        self.instances.push(Instance { prototype: id, root: u32::MAX, nodes });
+        let instance = self.instances.len() - 1;
+
+        self.sync_instance(instance, Some(parent));
+        self.instances[instance].root = self.instances[instance].nodes[root as usize];
+        self.instance_nodes.add(&self.instances[instance]);
+        Some(Handle::from(self.instances[instance].root))
+    }
+
+
+    /// Instance a node belongs to (its prototype id and the instance root).
+    pub fn instance_of(&self, handle: &Handle) -> Option<(u32, Handle)> {
+        let index = self.get_node(handle)?.index;
+        let entry = self.instance_nodes.get(&self.instances, index)?;
+        Some((entry.prototype, Handle::from(entry.root)))
+    }
+
+
+    /// Has an instance node been changed from its prototype node?
+    pub fn is_overridden(&self, handle: &Handle) -> bool {
+        let Some((prototype, node)) = self.prototype_node(handle) else { return false; };
+        let synced = self.prototypes[prototype as usize].synced.get(node as usize).cloned().flatten();
+        self.get_node(handle).map(Synced::from) != synced
+    }
+
+
+    /// Drop the overrides of an instance node, so it follows its prototype node again.
+    pub fn revert_override(&mut self, handle: &Handle) -> bool {
+        let Some((prototype, node)) = self.prototype_node(handle) else { return false; };
+        let Some(index) = self.get_node(handle).map(|node| node.index) else { return false; };
+        let Some(synced) = self.prototypes[prototype as usize].synced.get(node as usize).cloned().flatten() else { return false; };
+        self.nodes[index as usize].name = synced.name;
+        self.nodes[index as usize].attributes = synced.attributes;
+        self.set_components(&Handle::from(index), synced.components);
+        true
+    }
+
+
+    /// Turn an instance into a plain copy, no longer linked to its prototype.
+    /// Any node of the instance unpacks the whole instance.
+    pub fn unpack(&mut self, handle: &Handle) -> bool {
+        let Some((_, root)) = self.instance_of(handle) else { return false; };
+        let Some(position) = self.instances.iter().position(|instance| Some(instance.root) == root.index) else { return false; };
+        let instance = self.instances.remove(position);
+        self.instance_nodes.remove(&instance);
+        true
+    }
+
+
+    /// Prototype id and prototype node index of an instance node.
+    fn prototype_node(&self, handle: &Handle) -> Option<(u32, u32)> {
+        let index = self.get_node(handle)?.index;
+        let entry = self.instance_nodes.get(&self.instances, index)?;
+        Some((entry.prototype, entry.node))
+    }
+
+
+    /// Propagate a prototype's changes to its instances.
+    fn sync_prototype(&mut self, id: u32) {
+        // Instances whose root was removed are gone.
+        let nodes = &self.nodes;
+        let (kept, gone) = std::mem::take(&mut self.instances).into_iter()
+            .partition(|instance| nodes.get(instance.root as usize).is_some_and(|root| !root.removed));
+        self.instances = kept;
+        for instance in gone { self.instance_nodes.remove(&instance); }
+
+        for instance in 0..self.instances.len() {
+            if self.instances[instance].prototype == id { self.sync_instance(instance, None); }
+        }
+        let prototype = &mut self.prototypes[id as usize];
+        prototype.synced = Prototype::snapshot(&prototype.graph);
+    }
+
+
+    /// Bring an instance up to date with its prototype.
+    /// Missing nodes are created (the root under 'parent'), nodes removed from the prototype are removed,
+    /// nodes moved in the prototype are moved, and nodes still matching the last synced state take the prototype's current state.
+    fn sync_instance(&mut self, instance: usize, parent: Option<u32>) {
+        let prototype = &self.prototypes[self.instances[instance].prototype as usize];
+        let order = prototype.pre_order();
+        let current = Prototype::snapshot(&prototype.graph);
+        let synced = prototype.synced.clone();
+        let parents: Vec<u32> = prototype.graph.nodes.iter().map(|node| node.parent).collect();
+        let root = prototype.graph.get_root().map_or(u32::MAX, |root| root.index);
+        self.instances[instance].nodes.resize(current.len(), u32::MAX);
+
+        for (node, state) in current.iter().enumerate() {
+            let index = self.instances[instance].nodes[node];
+            if state.is_none() && index != u32::MAX {
+                self.remove(&Handle::from(index));
+                self.instances[instance].nodes[node] = u32::MAX;
+            }
+        }
+
+        for node in order {
+            let Some(state) = current[node as usize].clone() else { continue; };
+            let index = self.instances[instance].nodes[node as usize];
+            if index == u32::MAX {
+                let parent = if node == root { parent } else {
+                    Some(self.instances[instance].nodes[parents[node as usize] as usize])
+                };
+                let Some(parent) = parent.filter(|parent| self.get_node(&Handle::from(*parent)).is_some()) else { continue; };
+                let created = self.push_child_of(&state.name, &Handle::from(parent));
+                self.set_components(&created, state.components);
+                if let Some(node) = self.get_node_mut(&created) { node.attributes = state.attributes; }
+                self.instances[instance].nodes[node as usize] = created.index.unwrap_or(u32::MAX);
+                continue;
+            }
+
+            let Some(existing) = self.get_node(&Handle::from(index)).map(Synced::from) else { continue; };
+            if node != root {
+                let parent = self.instances[instance].nodes[parents[node as usize] as usize];
+                if parent != u32::MAX && self.nodes[index as usize].parent != parent {
+                    self.reparent(&Handle::from(index), &Handle::from(parent));
+                }
+            }
+            let Some(old) = synced.get(node as usize).cloned().flatten() else { continue; };
+            if existing.name == old.name { self.nodes[index as usize].name = state.name; }
+            if existing.attributes == old.attributes { self.nodes[index as usize].attributes = state.attributes; }
+            if existing.components == old.components { self.set_components(&Handle::from(index), state.components); }
+        }
+        self.instance_nodes.add(&self.instances[instance]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,177 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
+use super::{MachGraph, MachNode, Handle, Component, ComponentStore, Descendants};
+
+
+///
+/// Fetch.
+/// Component references a query borrows for each matching node: '&T' for a required component,
+/// 'Option<&T>' for an optional one, and tuples of those for several components.
+///
+pub trait Fetch<'a> {
+    /// Borrowed components of one node.
+    type Item;
+
+    /// Borrow the components of a node, if it has all required ones.
+    fn fetch(store: &'a ComponentStore, index: u32) -> Option<Self::Item>;
+
+    /// Nodes that can match (the smallest required storage), or None when nothing is required.
+    fn candidates(store: &'a ComponentStore) -> Option<&'a [u32]>;
+}
+
+
+///
+/// Required component.
+///
+impl<'a, C: Component> Fetch<'a> for &'a C {
+    type Item = &'a C;
+
+    fn fetch(store: &'a ComponentStore, index: u32) -> Option<Self::Item> {
+        store.get::<C>(index)
+    }
+
+    fn candidates(store: &'a ComponentStore) -> Option<&'a [u32]> {
+        Some(store.storage::<C>().map_or(&[], |storage| storage.nodes()))
+    }
+}
+
+
+///
+/// Optional component.
+///
+impl<'a, C: Component> Fetch<'a> for Option<&'a C> {
+    type Item = Option<&'a C>;
+
+    fn fetch(store: &'a ComponentStore, index: u32) -> Option<Self::Item> {
+        Some(store.get::<C>(index))
+    }
+
+    fn candidates(_store: &'a ComponentStore) -> Option<&'a [u32]> {
+        None
+    }
+}
+
+
+///
+/// Several components (tuples of up to six).
+///
+macro_rules! impl_fetch_tuple {
+    ($($name:ident),+) => {
+        impl<'a, $($name: Fetch<'a>),+> Fetch<'a> for ($($name,)+) {
+            type Item = ($($name::Item,)+);
+
+            fn fetch(store: &'a ComponentStore, index: u32) -> Option<Self::Item> {
+                Some(($($name::fetch(store, index)?,)+))
+            }
+
+            fn candidates(store: &'a ComponentStore) -> Option<&'a [u32]> {
+                [$($name::candidates(store)),+].into_iter()
+                    .flatten()
+                    .min_by_key(|nodes| nodes.len())
+            }
+        }
+    };
+}
+
+impl_fetch_tuple!(A);
+impl_fetch_tuple!(A, B);
+impl_fetch_tuple!(A, B, C);
+impl_fetch_tuple!(A, B, C, D);
+impl_fetch_tuple!(A, B, C, D, E);
+impl_fetch_tuple!(A, B, C, D, E, F);
+
+
+///
+/// Query.
+/// Nodes of a graph having a set of components, with borrowed references to those components.
+/// Built with MachGraph::query and narrowed with 'with', 'without' and 'subtree'.
+///
+/// Without a subtree or 'pre_order', nodes come in the storage order of the smallest required
+/// component, which is the fastest. Otherwise nodes come in pre-order.
+///
+pub struct Query<'a, Q, T = ()> {
+    graph: &'a MachGraph<T>,
+    with: Vec<TypeId>,
+    without: Vec<TypeId>,
+    start: Option<u32>,
+    pre_order: bool,
+    fetch: PhantomData<Q>,
+}
+
+
+///
+/// Implementation for Query.
+///
+impl<'a, Q: Fetch<'a>, T> Query<'a, Q, T> {
+    /// Only nodes that also have a component (not borrowed).
+    pub fn with<C: Component>(mut self) -> Self {
+        self.with.push(TypeId::of::<C>());
+        self
+    }
+
+
+    /// Only nodes that don't have a component.
+    pub fn without<C: Component>(mut self) -> Self {
+        self.without.push(TypeId::of::<C>());
+        self
+    }
+
+
+    /// Only nodes in the subtree of a node (node included), in pre-order.
+    pub fn subtree(mut self, handle: &Handle) -> Self {
+        self.start = Some(self.graph.get_node(handle).map_or(u32::MAX, |node| node.index));
+        self
+    }
+
+
+    /// Match in pre-order from the root.
+    pub fn pre_order(mut self) -> Self {
+        self.pre_order = true;
+        self
+    }
+
+
+    /// Iterate matching nodes with their components.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a MachNode<T>, Q::Item)> + '_ {
+        let graph = self.graph;
+        let start = match self.start {
+            Some(start) => Some(start),
+            None if self.pre_order => Some(graph.get_root().map_or(u32::MAX, |root| root.index)),
+            None => None,
+        };
+        let candidates: Box<dyn Iterator<Item = u32> + 'a> = match (start, Q::candidates(&graph.store)) {
+            (Some(start), _) => Box::new(Descendants::inclusive(&graph.nodes, start).map(|node| node.index)),
+            (None, Some(nodes)) => Box::new(nodes.iter().copied()),
+            (None, None) => Box::new(0..graph.nodes.len() as u32),
+        };
+        candidates.filter_map(move |index| self.get(index))
+    }
+
+
+    /// Components of a node, if it matches.
+    pub fn get(&self, index: u32) -> Option<(&'a MachNode<T>, Q::Item)> {
+        let store = &self.graph.store;
+        let node = self.graph.nodes.get(index as usize).filter(|node| !node.removed)?;
+        if !self.with.iter().all(|type_id| store.contains_type(*type_id, index)) { return None; }
+        if self.without.iter().any(|type_id| store.contains_type(*type_id, index)) { return None; }
+        Some((node, Q::fetch(store, index)?))
+    }
+}
+
+
+///
+/// Query implementation for MachGraph.
+///
+impl<T> MachGraph<T> {
+    /// Query nodes by their typed components, e.g. 'graph.query::<(&Transform, &Mesh)>().without::<Hidden>()'.
+    pub fn query<'a, Q: Fetch<'a>>(&'a self) -> Query<'a, Q, T> {
+        Query {
+            graph: self,
+            with: Vec::new(),
+            without: Vec::new(),
+            start: None,
+            pre_order: false,
+            fetch: PhantomData,
+        }
+    }
+}
\ No newline at end of file
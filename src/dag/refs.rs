@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use super::{MachNode, ComponentRef};
+
+
+///
+/// Reference counts of components across a graph's nodes.
+/// Counts are built from the nodes on first use (so deserialized graphs count correctly) and
+/// kept up to date by the graph's component methods afterwards.
+///
+#[derive(Debug, Default)]
+pub(crate) struct ComponentRefs {
+    /// Number of references per component (None until built).
+    counts: Option<HashMap<ComponentRef, usize>>,
+
+    /// Components whose count dropped to zero since the last collection.
+    orphans: Vec<ComponentRef>,
+}
+
+
+///
+/// Implementation for ComponentRefs.
+///
+impl ComponentRefs {
+    /// Build counts from nodes, unless already built.
+    pub(crate) fn prepare<T>(&mut self, nodes: &[MachNode<T>]) {
+        if self.counts.is_none() { self.recount(nodes); }
+    }
+
+
+    /// Rebuild counts from nodes.
+    pub(crate) fn recount<T>(&mut self, nodes: &[MachNode<T>]) {
+        let mut counts = HashMap::new();
+        for node in nodes.iter().filter(|node| !node.removed) {
+            for component in &node.components {
+                *counts.entry(*component).or_insert(0) += 1;
+            }
+        }
+        self.counts = Some(counts);
+    }
+
+
+    /// Number of references to a component, if counts are built.
+    pub(crate) fn get(&self, component: &ComponentRef) -> Option<usize> {
+        self.counts.as_ref().map(|counts| counts.get(component).copied().unwrap_or(0))
+    }
+
+
+    /// All counts, if built.
+    pub(crate) fn counts(&self) -> Option<&HashMap<ComponentRef, usize>> {
+        self.counts.as_ref()
+    }
+
+
+    /// Count a new reference.
+    pub(crate) fn add(&mut self, component: ComponentRef) {
+        if let Some(counts) = self.counts.as_mut() {
+            *counts.entry(component).or_insert(0) += 1;
+        }
+    }
+
+
+    /// Drop a reference, recording the component as orphaned when none are left.
+    pub(crate) fn release(&mut self, component: ComponentRef) {
+        let Some(counts) = self.counts.as_mut() else { return; };
+        if let Some(count) = counts.get_mut(&component) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&component);
+                self.orphans.push(component);
+            }
+        }
+    }
+
+
+    /// Take the orphaned components that are still unreferenced.
+    pub(crate) fn collect(&mut self) -> Vec<ComponentRef> {
+        let mut orphans = std::mem::take(&mut self.orphans);
+        if let Some(counts) = self.counts.as_ref() {
+            orphans.retain(|component| !counts.contains_key(component));
+        }
+        orphans.sort();
+        orphans.dedup();
+        orphans
+    }
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+
+///
+/// Component.
+/// Any type that can be stored in a ComponentStore.
+/// Components must be thread safe so graphs can still be shared across threads.
+///
+pub trait Component: Any + Send + Sync {}
+impl<T: Any + Send + Sync> Component for T {}
+
+
+///
+/// Storage.
+/// Dense storage for one component type (sparse set keyed by node index).
+/// Values are packed in a Vec, so iterating a component type touches contiguous memory.
+///
+pub struct Storage<T> {
+    /// Component values, packed.
+    dense: Vec<T>,
+
+    /// Node index owning each packed value.
+    owners: Vec<u32>,
+
+    /// Position in dense for each node index (u32::MAX means none).
+    sparse: Vec<u32>,
+}
+
+
+///
+/// Default implementation.
+///
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self {
+            dense: Vec::new(),
+            owners: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+}
+
+
+///
+/// Implementation for Storage.
+///
+impl<T> Storage<T> {
+    /// Number of stored components.
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+
+    /// Is empty?
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+
+    /// Does a node have this component?
+    pub fn contains(&self, index: u32) -> bool {
+        self.slot(index).is_some()
+    }
+
+
+    /// Node indices with this component, in storage order.
+    pub fn nodes(&self) -> &[u32] {
+        &self.owners
+    }
+
+
+    /// Get the component of a node.
+    pub fn get(&self, index: u32) -> Option<&T> {
+        self.slot(index).map(|slot| &self.dense[slot])
+    }
+
+
+    /// Get the component of a node mutable.
+    pub fn get_mut(&mut self, index: u32) -> Option<&mut T> {
+        self.slot(index).map(|slot| &mut self.dense[slot])
+    }
+
+
+    /// Insert the component of a node, returning the one it replaces.
+    pub fn insert(&mut self, index: u32, value: T) -> Option<T> {
+        if let Some(slot) = self.slot(index) {
+            return Some(std::mem::replace(&mut self.dense[slot], value));
+        }
+        let u = index as usize;
+        if u >= self.sparse.len() { self.sparse.resize(u + 1, u32::MAX); }
+        self.sparse[u] = self.dense.len() as u32;
+        self.dense.push(value);
+        self.owners.push(index);
+        None
+    }
+
+
+    /// Remove the component of a node.
+    /// The last value is moved into the freed slot to keep storage dense.
+    pub fn remove(&mut self, index: u32) -> Option<T> {
+        let slot = self.slot(index)?;
+        self.sparse[index as usize] = u32::MAX;
+        let value = self.dense.swap_remove(slot);
+        self.owners.swap_remove(slot);
+        if let Some(moved) = self.owners.get(slot) {
+            self.sparse[*moved as usize] = slot as u32;
+        }
+        Some(value)
+    }
+
+
+    /// Iterate (node index, component) pairs in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.owners.iter().copied().zip(self.dense.iter())
+    }
+
+
+    /// Iterate (node index, component) pairs mutable in storage order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &mut T)> {
+        self.owners.iter().copied().zip(self.dense.iter_mut())
+    }
+
+
+    /// Position of a node's component in dense storage.
+    fn slot(&self, index: u32) -> Option<usize> {
+        match self.sparse.get(index as usize) {
+            Some(slot) if *slot != u32::MAX => Some(*slot as usize),
+            _ => None,
+        }
+    }
+}
+
+
+///
+/// Type erased storage, so one store can hold storages of any component type.
+///
+trait AnyStorage: Send + Sync {
+    fn contains(&self, index: u32) -> bool;
+    fn release(&mut self, index: u32) -> bool;
+    fn len(&self) -> usize;
+    fn type_name(&self) -> &'static str;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+
+impl<T: Component> AnyStorage for Storage<T> {
+    fn contains(&self, index: u32) -> bool {
+        self.slot(index).is_some()
+    }
+
+    fn release(&mut self, index: u32) -> bool {
+        self.remove(index).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+
+///
+/// ComponentStore.
+/// Typed component data for the nodes of a graph, keyed by component type and node index.
+/// A node has at most one component of each type.
+///
+#[derive(Default)]
+pub struct ComponentStore {
+    /// Storage for each component type.
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+
+///
+/// Debug implementation (component type names with counts).
+///
+impl fmt::Debug for ComponentStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.storages.values().map(|storage| (storage.type_name(), storage.len())))
+            .finish()
+    }
+}
+
+
+///
+/// Implementation for ComponentStore.
+///
+impl ComponentStore {
+    /// New empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /// Number of component types with storage.
+    pub fn type_count(&self) -> usize {
+        self.storages.len()
+    }
+
+
+    /// Storage of a component type.
+    pub fn storage<T: Component>(&self) -> Option<&Storage<T>> {
+        self.storages.get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref::<Storage<T>>())
+    }
+
+
+    /// Storage of a component type mutable.
+    pub fn storage_mut<T: Component>(&mut self) -> Option<&mut Storage<T>> {
+        self.storages.get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<Storage<T>>())
+    }
+
+
+    /// Does a node have a component of this type?
+    pub fn contains<T: Component>(&self, index: u32) -> bool {
+        self.storage::<T>().is_some_and(|storage| storage.contains(index))
+    }
+
+
+    /// Does a node have a component of a type (by type id)?
+    pub fn contains_type(&self, type_id: TypeId, index: u32) -> bool {
+        self.storages.get(&type_id).is_some_and(|storage| storage.contains(index))
+    }
+
+
+    /// Get the component of a node.
+    pub fn get<T: Component>(&self, index: u32) -> Option<&T> {
+        self.storage::<T>()?.get(index)
+    }
+
+
+    /// Get the component of a node mutable.
+    pub fn get_mut<T: Component>(&mut self, index: u32) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(index)
+    }
+
+
+    /// Insert the component of a node, returning the one it replaces.
+    pub fn insert<T: Component>(&mut self, index: u32, value: T) -> Option<T> {
+        self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T>::default()))
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .and_then(|storage| storage.insert(index, value))
+    }
+
+
+    /// Remove the component of a node.
+    pub fn remove<T: Component>(&mut self, index: u32) -> Option<T> {
+        self.storage_mut::<T>()?.remove(index)
+    }
+
+
+    /// Drop all components of a node. Returns the number dropped.
+    pub fn release(&mut self, index: u32) -> usize {
+        self.storages.values_mut()
+            .map(|storage| storage.release(index))
+            .filter(|released| *released)
+            .count()
+    }
+
+
+    /// Iterate (node index, component) pairs of a component type.
+    pub fn iter<T: Component>(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.storage::<T>().into_iter().flat_map(|storage| storage.iter())
+    }
+
+
+    /// Iterate (node index, component) pairs of a component type mutable.
+    pub fn iter_mut<T: Component>(&mut self) -> impl Iterator<Item = (u32, &mut T)> {
+        self.storage_mut::<T>().into_iter().flat_map(|storage| storage.iter_mut())
+    }
+
+
+    /// Drop all components.
+    pub fn clear(&mut self) {
+        self.storages.clear();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Serialize, Deserialize};
+
+
+///
+/// Tags.
+/// Inverted index from tag to the indices of the nodes carrying it, so "all nodes with tag X"
+/// costs only the size of the result. Serialized as '{"tag": [indices...]}'.
+///
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tags {
+    index: BTreeMap<String, BTreeSet<u32>>,
+}
+
+
+///
+/// Implementation for Tags.
+///
+impl Tags {
+    /// No tags at all?
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+
+    /// Tag names in use, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+
+    /// Tag a node. Returns false if it already had the tag.
+    pub fn insert(&mut self, index: u32, tag: &str) -> bool {
+        match self.index.get_mut(tag) {
+            Some(nodes) => nodes.insert(index),
+            None => {
+                self.index.insert(String::from(tag), BTreeSet::from([index]));
+                true
+            },
+        }
+    }
+
+
+    /// Untag a node. Returns false if it didn't have the tag.
+    pub fn remove(&mut self, index: u32, tag: &str) -> bool {
+        let Some(nodes) = self.index.get_mut(tag) else { return false; };
+        let removed = nodes.remove(&index);
+        if nodes.is_empty() { self.index.remove(tag); }
+        removed
+    }
+
+
+    /// Does a node have a tag?
+    pub fn contains(&self, index: u32, tag: &str) -> bool {
+        self.index.get(tag).is_some_and(|nodes| nodes.contains(&index))
+    }
+
+
+    /// Nodes with a tag, in index order.
+    pub fn nodes(&self, tag: &str) -> impl Iterator<Item = u32> + '_ {
+        self.index.get(tag).into_iter().flatten().copied()
+    }
+
+
+    /// Number of nodes with a tag.
+    pub fn count(&self, tag: &str) -> usize {
+        self.index.get(tag).map_or(0, BTreeSet::len)
+    }
+
+
+    /// Tags of a node, in order.
+    pub fn of(&self, index: u32) -> impl Iterator<Item = &str> {
+        self.index.iter()
+            .filter(move |(_, nodes)| nodes.contains(&index))
+            .map(|(tag, _)| tag.as_str())
+    }
+
+
+    /// Nodes with all of the tags (intersection).
+    pub fn all(&self, tags: &[&str]) -> BTreeSet<u32> {
+        let mut sets: Vec<&BTreeSet<u32>> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            match self.index.get(*tag) {
+                Some(nodes) => sets.push(nodes),
+                None => return BTreeSet::new(),
+            }
+        }
+        sets.sort_by_key(|nodes| nodes.len());
+        let Some((smallest, rest)) = sets.split_first() else { return BTreeSet::new(); };
+        smallest.iter()
+            .filter(|index| rest.iter().all(|nodes| nodes.contains(index)))
+            .copied()
+            .collect()
+    }
+
+
+    /// Nodes with any of the tags (union).
+    pub fn any(&self, tags: &[&str]) -> BTreeSet<u32> {
+        tags.iter().flat_map(|tag| self.nodes(tag)).collect()
+    }
+
+
+    /// Nodes with a tag but none of the others (difference).
+    pub fn except(&self, tag: &str, without: &[&str]) -> BTreeSet<u32> {
+        self.nodes(tag)
+            .filter(|index| !without.iter().any(|other| self.contains(*index, other)))
+            .collect()
+    }
+
+
+    /// Remove a tag from every node. Returns the nodes that had it.
+    pub fn clear_tag(&mut self, tag: &str) -> BTreeSet<u32> {
+        self.index.remove(tag).unwrap_or_default()
+    }
+
+
+    /// Remove every tag of a node (when the node is removed).
+    pub fn release(&mut self, index: u32) {
+        self.index.retain(|_, nodes| {
+            nodes.remove(&index);
+            !nodes.is_empty()
+        });
+    }
+}
\ No newline at end of file
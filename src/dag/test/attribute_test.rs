@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod attribute {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Buttons get a color, eyes a size, and the hat a few values of each type.
+    fn decorated() -> MachGraph {
+        let mut graph = snowman();
+        graph.set_attribute(&Handle::from("bottom_button"), "color", "red");
+        graph.set_attribute(&Handle::from("middle_button"), "color", "blue");
+        graph.set_attribute(&Handle::from("top_button"), "color", "red");
+        graph.set_attribute(&Handle::from("top_button"), "shiny", true);
+        graph.set_attribute(&Handle::from(10), "size", 2);
+        graph.set_attribute(&Handle::from(11), "size", 2.5);
+        let hat = Handle::from("hat");
+        graph.set_attribute(&hat, "source", "hats/top.mesh");
+        graph.set_attribute(&hat, "tags", vec!["formal", "black"]);
+        graph.set_attribute(&hat, "height", 1.5);
+        graph
+    }
+
+    #[test]
+    fn values() {
+        let mut graph = decorated();
+        let hat = Handle::from("hat");
+        assert_eq!(graph.get_attribute(&hat, "source").and_then(Attribute::as_str), Some("hats/top.mesh"));
+        assert_eq!(graph.get_attribute(&hat, "height").and_then(Attribute::as_float), Some(1.5));
+        assert_eq!(graph.get_attribute(&Handle::from(10), "size").and_then(Attribute::as_float), Some(2.0));
+        assert_eq!(graph.get_attribute(&Handle::from(10), "size").and_then(Attribute::as_bool), None);
+        assert_eq!(graph.get_attribute(&hat, "tags").unwrap().to_string(), "[formal, black]");
+        assert_eq!(graph.set_attribute(&hat, "height", 2), Some(Attribute::Float(1.5)));
+        assert!(graph.set_attribute(&Handle::from("missing"), "height", 2).is_none());
+
+        let node = graph.get_node(&hat).unwrap();
+        let keys: Vec<&String> = node.attributes().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["height", "source", "tags"]);
+
+        for key in ["height", "source", "tags"] {
+            assert!(graph.remove_attribute(&hat, key).is_some());
+        }
+        assert!(graph.get_node(&hat).unwrap().attributes.is_none());
+        assert!(graph.get_root().unwrap().attributes().next().is_none());
+    }
+
+    #[test]
+    fn serialize() {
+        let graph = decorated();
+        let json = serde_json::to_string(&graph).unwrap();
+        assert!(json.contains("\"attributes\":{\"height\":1.5,\"source\":\"hats/top.mesh\",\"tags\":[\"formal\",\"black\"]}"));
+        assert!(json.contains("\"attributes\":{\"size\":2}"));
+        assert_eq!(json.matches("\"attributes\"").count(), 6);
+
+        let back: MachGraph = serde_json::from_str(&json).unwrap();
+        for (node, original) in back.nodes.iter().zip(graph.nodes.iter()) {
+            assert_eq!(node.attributes, original.attributes);
+        }
+        assert_eq!(back.get_attribute(&Handle::from(11), "size"), Some(&Attribute::Float(2.5)));
+        assert_eq!(back.get_attribute(&Handle::from(10), "size"), Some(&Attribute::Int(2)));
+    }
+
+    #[test]
+    fn path_queries() {
+        let graph = decorated();
+        assert_eq!(Handle::index(&graph, "mid.*[color=blue]"), Some(7));
+        assert_eq!(Handle::index(&graph, "mid.*[color=red][shiny]"), Some(8));
+        assert_eq!(Handle::index(&graph, "top.*[size=2.5]"), Some(11));
+        assert_eq!(Handle::index(&graph, "hat[source=\"hats/top.mesh\"]"), Some(12));
+        assert_eq!(Handle::index(&graph, "root.*[tags=black]"), Some(12));
+        assert_eq!(Handle::index(&graph, "mid.*[color=green]"), None);
+        assert_eq!(Handle::index(&graph, "arms.*"), Some(14));
+        assert_eq!(Handle::index(&graph, "root.body.top.left"), Some(10));
+        assert_eq!(Handle::index(&graph, ""), None);
+
+        let indices = |handles: Vec<Handle>| -> Vec<u32> { handles.iter().filter_map(|handle| handle.index).collect() };
+        assert_eq!(indices(graph.select("*[color=red]")), vec![6, 8]);
+        assert_eq!(indices(graph.select("[color]")), vec![6, 7, 8]);
+        assert_eq!(indices(graph.select("body.*.*[size]")), vec![10, 11]);
+        assert_eq!(indices(graph.select("left")), vec![3, 10, 14]);
+        assert_eq!(indices(graph.select("arms.left")), vec![14]);
+        assert_eq!(indices(graph.select("root.*")), vec![1, 12, 13]);
+        assert!(graph.select("hat.*").is_empty());
+    }
+
+    #[test]
+    fn prototypes() {
+        let mut graph = MachGraph::default();
+        let button = graph.create_prototype("button");
+        graph.edit_prototype(button, |prototype| prototype.set_attribute(&prototype.root.clone(), "color", "black"));
+        let first = graph.instantiate(button, &graph.root.clone()).unwrap();
+        let second = graph.instantiate(button, &graph.root.clone()).unwrap();
+        graph.set_attribute(&second, "color", "gold");
+
+        graph.edit_prototype(button, |prototype| prototype.set_attribute(&prototype.root.clone(), "color", "white"));
+        assert_eq!(graph.get_attribute(&first, "color").and_then(Attribute::as_str), Some("white"));
+        assert_eq!(graph.get_attribute(&second, "color").and_then(Attribute::as_str), Some("gold"));
+        assert!(graph.is_overridden(&second));
+    }
+}
\ No newline at end of file
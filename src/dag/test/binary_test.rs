@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod binary {
+    use crate::dag::*;
+
+    /// Snowman with components, attributes, tags, edges, a removed node and a button prototype.
+    fn dressed() -> MachGraph {
+        MachGraph::from_json(include_str!("data/snowman_v1.json")).unwrap()
+    }
+
+    /// Wide and deep graph, so nodes and strings span several blocks.
+    fn large() -> MachGraph {
+        let mut graph = MachGraph::new("large");
+        let mut parent = graph.root.clone();
+        for index in 1..300 {
+            let name = format!("node{}", index % 100);
+            let node = graph.push_child_of(&name, &parent);
+            graph.push_component(&node, (index % 3, index));
+            if index % 10 == 0 { parent = node; }
+        }
+        graph
+    }
+
+    #[test]
+    fn round_trip() {
+        let graph = dressed();
+        let bytes = graph.to_binary().unwrap();
+        let back: MachGraph = MachGraph::from_binary(&bytes).unwrap();
+        assert_eq!(back.to_json_pretty().unwrap(), include_str!("data/snowman_v1.json"));
+        assert!(bytes.len() * 2 < graph.to_json().unwrap().len());
+
+        let graph = large();
+        let back: MachGraph = MachGraph::from_binary(&graph.to_binary().unwrap()).unwrap();
+        assert_eq!(back.to_json().unwrap(), graph.to_json().unwrap());
+
+        // Payloads are written.
+        let mut weighted = MachGraph::with_root("weights", 1.5);
+        let body = weighted.push_child_with("body", &Handle::from(0), 2.0).unwrap();
+        weighted.push_child_with("base", &body, 10.0);
+        weighted.remove(&body);
+        weighted.push_child_with("hat", &Handle::from(0), 0.25);
+        let back = MachGraph::<f32>::from_binary(&weighted.to_binary().unwrap()).unwrap();
+        assert_eq!(back.to_json().unwrap(), weighted.to_json().unwrap());
+        assert_eq!(back.get_data(&Handle::from("hat")), Some(&0.25));
+
+        // Payloads the reader can't decode are an error.
+        let named = MachGraph::with_root("names", String::from("root"));
+        assert_eq!(MachGraph::<f32>::from_binary(&named.to_binary().unwrap()).unwrap_err().to_string(),
+            "invalid graph file: payload of node 0: invalid type: string \"root\", expected f32 at line 1 column 6");
+
+        // Attribute lists are nested at most MAX_DEPTH deep.
+        let mut deep = MachGraph::new("deep");
+        let mut value = Attribute::Int(1);
+        for _ in 0..MAX_DEPTH {
+            value = Attribute::List(vec![value]);
+        }
+        deep.set_attribute(&Handle::from(0), "deep", value.clone());
+        let back: MachGraph = MachGraph::from_binary(&deep.to_binary().unwrap()).unwrap();
+        assert_eq!(back.get_attribute(&Handle::from(0), "deep"), Some(&value));
+        deep.set_attribute(&Handle::from(0), "deep", Attribute::List(vec![value]));
+        assert_eq!(deep.to_binary().unwrap_err().to_string(), "invalid graph file: attribute lists nested deeper than 128");
+    }
+
+    #[test]
+    fn golden() {
+        assert_eq!(dressed().to_binary().unwrap(), include_bytes!("data/snowman_v1.bin"));
+    }
+
+    #[test]
+    fn sibling_order() {
+        let mut graph = dressed();
+        assert!(graph.reparent(&Handle::from("hat"), &Handle::from("body")));
+        assert!(graph.reparent(&Handle::from("base"), &Handle::from(0)));
+        let bytes = graph.to_binary().unwrap();
+        let back: MachGraph = MachGraph::from_binary(&bytes).unwrap();
+        let reader = BinaryGraph::new(&bytes).unwrap();
+        let children = |index| reader.children(index).unwrap().iter().map(|child| child.index).collect::<Vec<_>>();
+        assert_eq!(children(0), vec![1, 13, 2]);
+        assert_eq!(children(1), vec![5, 9, 12]);
+        assert_eq!(graph.get_root().unwrap().children, vec![1, 13, 2]);
+        assert_eq!(back.get_root().unwrap().children, vec![1, 13, 2]);
+        assert_eq!(back.get_node(&Handle::from("body")).unwrap().children, vec![5, 9, 12]);
+
+        // Shared children keep their parent.
+        let shared = graph.push_child_of("shared", &Handle::from("arms"));
+        graph.get_node_mut(&Handle::from("body")).unwrap().children.insert(0, shared.index.unwrap());
+        let bytes = graph.to_binary().unwrap();
+        let back: MachGraph = MachGraph::from_binary(&bytes).unwrap();
+        assert_eq!(back.to_json().unwrap(), graph.to_json().unwrap());
+        assert_eq!(back.get_node(&shared).unwrap().parent, 13);
+        let reader = BinaryGraph::new(&bytes).unwrap();
+        assert_eq!(reader.children(1).unwrap()[0].name, "shared");
+        assert_eq!(reader.children(13).unwrap().last().unwrap().name, "shared");
+
+        // Children that form a cycle aren't written.
+        let arms = graph.get_node(&Handle::from("arms")).unwrap().index;
+        graph.get_node_mut(&shared).unwrap().children.push(arms);
+        assert!(matches!(graph.to_binary(), Err(GraphError::Cycle(_))));
+    }
+
+    #[test]
+    fn reader() {
+        for graph in [dressed(), large()] {
+            let bytes = graph.to_binary().unwrap();
+            let reader = BinaryGraph::new(&bytes).unwrap();
+            assert_eq!(reader.len(), graph.nodes.len());
+            assert_eq!(reader.root(), 0);
+            assert_eq!(reader.name().unwrap(), graph.name);
+            for node in &graph.nodes {
+                let Some(read) = reader.node(node.index).unwrap() else {
+                    assert!(node.removed);
+                    continue;
+                };
+                assert_eq!((read.index, read.name, read.parent), (node.index, node.name.as_str(), node.parent));
+                assert_eq!(read.components().collect::<Vec<_>>(), node.components);
+                assert_eq!(reader.children(node.index).unwrap().iter().map(|child| child.index).collect::<Vec<_>>(), node.children);
+                let path = Handle::path(&graph, node.index).unwrap();
+                assert_eq!(reader.path(node.index).unwrap(), Some(path.clone()));
+                assert_eq!(reader.index(&path).unwrap(), Handle::index(&graph, &path));
+            }
+            assert_eq!(reader.nodes().map(Result::unwrap).count(), graph.nodes.iter().filter(|node| !node.removed).count());
+            assert!(reader.node(graph.nodes.len() as u32).unwrap().is_none());
+        }
+
+        let bytes = dressed().to_binary().unwrap();
+        let reader = BinaryGraph::new(&bytes).unwrap();
+        assert!(reader.node(8).unwrap().is_none());
+        assert_eq!(reader.get_node(&Handle::from("root.body.top.button.hole")).unwrap().unwrap().index, 17);
+        assert_eq!(reader.get_node(&Handle::from(13)).unwrap().unwrap().name, "arms");
+        assert_eq!(reader.index("arms.*").unwrap(), Some(14));
+        assert_eq!(reader.index("mid.top_button").unwrap(), None);
+        for path in ["hat[height]", "[color=white]", "root.[color=white].hat", "body[color=red]", "root.*[sizes].x", "hat[height=0.25]"] {
+            assert_eq!(reader.index(path).unwrap(), Handle::index(&dressed(), path), "{}", path);
+        }
+        assert_eq!(reader.index("hat[height]").unwrap(), Some(12));
+        assert_eq!(reader.attributes(1).unwrap().unwrap()["color"], Attribute::from("white"));
+        assert_eq!(reader.attributes(2).unwrap(), None);
+        assert_eq!(reader.children(5).unwrap().iter().map(|node| node.name).collect::<Vec<_>>(), vec!["bottom_button", "middle_button"]);
+        assert_eq!(reader.get_node(&Handle::from("body")).unwrap().unwrap().components().collect::<Vec<_>>(), vec![ComponentRef::from(4), ComponentRef::new(2, 9)]);
+    }
+
+    #[test]
+    fn bad_files() {
+        let message = |bytes: &[u8]| match BinaryGraph::new(bytes) {
+            Err(error) => error.to_string(),
+            Ok(_) => panic!("file not rejected"),
+        };
+        let bytes = dressed().to_binary().unwrap();
+        assert_eq!(message(&bytes[..20]), "invalid graph file: not a binary graph file");
+        assert_eq!(message(b"{\"version\":1,\"name\":\"g\",\"root\":0,\"nodes\":[]}"), "invalid graph file: not a binary graph file");
+        assert_eq!(message(&bytes[..bytes.len() - 1]), "invalid graph file: truncated binary graph file");
+
+        // The checksum is checked when opening, unless asked not to, and always when decoding.
+        let mut corrupt = bytes.clone();
+        corrupt[60] ^= 1;
+        assert_eq!(message(&corrupt), "invalid graph file: checksum mismatch");
+        let reader = BinaryGraph::new_unchecked(&corrupt).unwrap();
+        assert_eq!(reader.verify().unwrap_err().to_string(), "invalid graph file: checksum mismatch");
+        assert_eq!(reader.to_graph::<()>().unwrap_err().to_string(), "invalid graph file: checksum mismatch");
+        assert_eq!(MachGraph::<()>::from_binary(&corrupt).unwrap_err().to_string(), "invalid graph file: checksum mismatch");
+        assert!(BinaryGraph::new_unchecked(&bytes).unwrap().verify().is_ok());
+
+        // A bad record is an error, not the end of the nodes.
+        let mut bad = large().to_binary().unwrap();
+        let u32_at = |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let record = u32_at(&bad, u32_at(&bad, 20) + 4);
+        bad[record] = 0x7f;
+        let reader = BinaryGraph::new_unchecked(&bad).unwrap();
+        let nodes: Vec<_> = reader.nodes().collect();
+        assert_eq!(nodes.len(), 65);
+        assert_eq!(nodes[64].as_ref().unwrap_err().to_string(), "invalid graph file: bad record of node 64");
+        assert!(reader.node(63).unwrap().is_some());
+        assert!(reader.node(64).is_err());
+        assert!(reader.index("node99").is_err());
+
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert_eq!(message(&future), "unsupported graph file version 2");
+
+        // A parent cycle has no path, but decodes as it was.
+        let mut graph = MachGraph::new("g");
+        let a = graph.push_child("a");
+        let b = graph.push_child_of("b", &a);
+        graph.get_node_mut(&a).unwrap().parent = b.index.unwrap();
+        let bytes = graph.to_binary().unwrap();
+        let reader = BinaryGraph::new(&bytes).unwrap();
+        assert_eq!(reader.path(1).unwrap(), None);
+        assert_eq!(reader.to_graph::<()>().unwrap().get_node(&a).unwrap().parent, 2);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_file() {
+        let path = std::env::temp_dir().join(format!("mach_core_{}.mach", std::process::id()));
+        std::fs::write(&path, large().to_binary().unwrap()).unwrap();
+        let file = unsafe { BinaryFile::open(&path) }.unwrap();
+        let reader = file.graph().unwrap();
+        assert_eq!(reader.path(299).unwrap(), Handle::path(&large(), 299));
+        assert_eq!(reader.to_graph::<()>().unwrap().nodes.len(), 300);
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(unsafe { BinaryFile::open(&path) }, Err(GraphError::Io(_))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod combinators {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    #[derive(Default)]
+    struct RenameVisitor {}
+    impl Visitor for RenameVisitor {
+        fn visit_mut(&mut self, node: &mut MachNode) {
+            node.name = node.name.to_uppercase();
+        }
+    }
+
+    #[test]
+    fn collect() {
+        let graph = snowman();
+
+        let names = Collect::names();
+        graph.pre_visit_from(&Handle::from("arms"), None, true, &names).unwrap();
+        assert_eq!(*names.items(), vec!["arms", "left", "right"]);
+
+        let handles = Collect::handles();
+        graph.post_visit_from(&Handle::from("top"), None, false, &handles).unwrap();
+        let handles = handles.into_items();
+        assert_eq!(handles.len(), 2);
+        assert_eq!(handles[1].index, Some(11));
+        assert_eq!(graph.get_node(&handles[0]).unwrap().name, "left");
+
+        let lengths = Collect::new(|node: &MachNode| node.name.len());
+        graph.visit_all(&lengths);
+        assert_eq!(lengths.items().iter().sum::<usize>(), 88);
+    }
+
+    #[test]
+    fn filter() {
+        let graph = snowman();
+        let buttons = Collect::indices().filter(|node: &MachNode| node.name.ends_with("_button"));
+        graph.pre_visit(&buttons).unwrap();
+        assert_eq!(*buttons.inner.items(), vec![6, 7, 8]);
+
+        let mut graph = snowman();
+        let mut leaves = RenameVisitor::default().filter(|node: &MachNode| !node.has_children());
+        graph.pre_visit_mut(&mut leaves).unwrap();
+        assert_eq!(graph.nodes[3].name, "LEFT");
+        assert_eq!(graph.nodes[12].name, "HAT");
+        assert_eq!(graph.nodes[2].name, "base");
+    }
+
+    #[test]
+    fn chain() {
+        let mut graph = snowman();
+        let mut names = Collect::names();
+        let mut counts = CountByDepth::default();
+        let mut both = RenameVisitor::default().chain(&mut names).chain(&mut counts);
+        graph.pre_visit_mut(&mut both).unwrap();
+
+        assert_eq!(names.items()[..3], ["ROOT", "BODY", "BASE"]);
+        assert_eq!(counts.total(), 16);
+        assert_eq!(counts.counts(), vec![1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn count_by_depth() {
+        let graph = snowman();
+
+        let counts = CountByDepth::default();
+        graph.post_visit_from(&Handle::from("body"), None, true, &counts).unwrap();
+        assert_eq!(counts.counts(), vec![1, 3, 7]);
+
+        let counts = CountByDepth::default();
+        graph.visit_all(&counts);
+        assert_eq!(counts.counts(), vec![1, 3, 5, 7]);
+
+        let counts = CountByDepth::default();
+        graph.pre_visit_from(&Handle::from("root"), Some(1), false, &counts).unwrap();
+        assert_eq!(counts.counts(), vec![3]);
+        assert!(CountByDepth::default().counts().is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod commands {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Adds a "glove" under every arm and removes the buttons.
+    #[derive(Default)]
+    struct DressVisitor {
+        pub gloves: Vec<CommandHandle>,
+    }
+    impl CommandVisitor for DressVisitor {
+        fn visit(&mut self, node: &MachNode, commands: &mut GraphCommands) {
+            if node.name.ends_with("_button") {
+                commands.remove(Handle::from(node.index));
+            }
+            if node.parent == 13 && node.has_parent() {
+                let glove = commands.push_child_of("glove", Handle::from(node.index));
+                commands.push_component(&glove, node.index);
+                self.gloves.push(glove);
+            }
+        }
+    }
+
+    #[test]
+    fn record_and_apply() {
+        let mut graph = snowman();
+        let mut commands = GraphCommands::new();
+        let scarf = commands.push_child_of("scarf", Handle::from("body"));
+        let knot = commands.push_child_of("knot", &scarf);
+        commands.push_component(&knot, 42);
+        commands.reparent(Handle::from("hat"), &scarf);
+        commands.remove(Handle::from("arms"));
+        assert_eq!(commands.len(), 5);
+
+        let applied = commands.apply(&mut graph).unwrap();
+        let scarf = applied.resolve(&scarf).expect("scarf not created");
+        let knot = applied.resolve(&knot).expect("knot not created");
+        assert_eq!(scarf.index, Some(16));
+        assert_eq!(knot.index, Some(17));
+        assert_eq!(graph.get_node(&knot).unwrap().components, vec![ComponentRef::from(42)]);
+        assert_eq!(Handle::path(&graph, 12).unwrap(), "root.body.scarf.hat");
+        assert!(graph.get_node(&Handle::from("arms")).is_none());
+        assert_eq!(applied.created().len(), 2);
+    }
+
+    #[test]
+    fn all_or_nothing() {
+        let mut graph = snowman();
+        let message = |commands: GraphCommands, graph: &mut MachGraph| commands.apply(graph).unwrap_err().to_string();
+
+        let mut commands = GraphCommands::new();
+        let scarf = commands.push_child_of("scarf", Handle::from("body"));
+        commands.push_component(&scarf, 42);
+        commands.remove(Handle::from("arms"));
+        let orphan = commands.push_child_of("orphan", Handle::from("missing"));
+        commands.push_child_of("orphan_child", &orphan);
+        assert_eq!(message(commands, &mut graph), "command 3 can't be applied: node 'missing' was not found");
+
+        // Later commands see the edits of earlier ones.
+        let mut commands = GraphCommands::new();
+        commands.remove(Handle::from("arms"));
+        commands.push_component(Handle::from(14), 42);
+        assert_eq!(message(commands, &mut graph), "command 1 can't be applied: node 14 was not found");
+
+        let mut commands = GraphCommands::new();
+        commands.reparent(Handle::from("mid"), Handle::from("hat"));
+        commands.reparent(Handle::from("hat"), Handle::from("middle_button"));
+        assert_eq!(message(commands, &mut graph), "command 1 can't be applied: node 'hat' can't be moved under node 'middle_button'");
+
+        let mut commands = GraphCommands::new();
+        commands.remove(graph.root.clone());
+        assert_eq!(message(commands, &mut graph), "command 0 can't be applied: the root can't be removed");
+
+        // Nothing was applied.
+        assert_eq!(graph.nodes.len(), 16);
+        assert!(graph.nodes.iter().all(|node| !node.removed && node.components.is_empty()));
+        assert_eq!(Handle::path(&graph, 5).unwrap(), "root.body.mid");
+    }
+
+    #[test]
+    fn deferred_visit() {
+        let mut graph = snowman();
+        let mut visitor = DressVisitor::default();
+        let applied = graph.pre_visit_deferred(&mut visitor).unwrap();
+
+        assert_eq!(visitor.gloves.len(), 2);
+        let left_glove = applied.resolve(&visitor.gloves[0]).unwrap();
+        assert_eq!(left_glove.path, "root.arms.left.glove");
+        assert_eq!(graph.get_node(&left_glove).unwrap().components, vec![ComponentRef::from(14)]);
+        assert!(graph.get_node(&Handle::from("mid")).unwrap().children.is_empty());
+        assert!(graph.get_node(&Handle::from("middle_button")).is_none());
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 15);
+    }
+
+    #[test]
+    fn empty() {
+        let mut graph = snowman();
+        let commands = GraphCommands::new();
+        assert!(commands.is_empty());
+        let applied = commands.apply(&mut graph).unwrap();
+        assert!(applied.created().is_empty());
+        assert_eq!(graph.nodes.len(), 16);
+    }
+
+    #[test]
+    fn removed_handles() {
+        let mut graph = snowman();
+        let mid = Handle::from(5);
+        assert!(graph.remove(&mid));
+        assert_eq!(mid.get_index(&graph), None);
+        assert_eq!(Handle::from(99).get_index(&graph), None);
+
+        // Nothing is pushed under a removed parent.
+        let len = graph.nodes.len();
+        assert!(graph.push_child_with("button", &mid, ()).is_none());
+        assert_eq!(graph.push_child_of("button", &mid).index, None);
+        assert_eq!(graph.nodes.len(), len);
+
+        let names = Collect::names();
+        graph.pre_visit_from(&mid, None, true, &names).unwrap();
+        assert!(names.items().is_empty());
+
+        let mut commands = GraphCommands::new();
+        commands.push_child_of("button", mid);
+        assert!(commands.apply(&mut graph).is_err());
+        assert_eq!(graph.nodes.len(), len);
+    }
+}
\ No newline at end of file
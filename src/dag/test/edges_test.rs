@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod edges {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Buttons reference the hat, the body depends on the arms (with a weight).
+    fn connected() -> MachGraph {
+        let mut graph = snowman();
+        for name in ["bottom_button", "middle_button", "top_button"] {
+            assert!(graph.connect(&Handle::from(name), &Handle::from("hat"), "references"));
+        }
+        assert!(graph.connect_with(&Handle::from("body"), &Handle::from("arms"), "depends_on", 2.5));
+        graph
+    }
+
+    fn names<'a>(nodes: impl Iterator<Item = &'a MachNode>) -> Vec<&'a str> {
+        nodes.map(|node| node.name.as_str()).collect()
+    }
+
+    #[test]
+    fn connect() {
+        let mut graph = connected();
+        assert_eq!(graph.edges.len(), 4);
+        assert!(!graph.connect(&Handle::from("top_button"), &Handle::from("hat"), "references"));
+        assert!(graph.connect(&Handle::from("top_button"), &Handle::from("hat"), "targets"));
+        assert!(!graph.connect(&Handle::from("top_button"), &Handle::from("scarf"), "targets"));
+        assert_eq!(graph.edges.len(), 5);
+
+        let edge = graph.edge(&Handle::from("body"), &Handle::from("arms"), "depends_on").unwrap();
+        assert_eq!(edge, &Edge::with_data(1, 13, "depends_on", 2.5));
+        assert_eq!(graph.edge(&Handle::from("arms"), &Handle::from("body"), "depends_on"), None);
+
+        // Connecting again replaces the data.
+        assert!(graph.connect_with(&Handle::from("body"), &Handle::from("arms"), "depends_on", "always"));
+        graph.edge_mut(&Handle::from("middle_button"), &Handle::from("hat"), "references").unwrap().data = Some(Attribute::from(1));
+        assert_eq!(graph.edges.labelled("depends_on").count(), 1);
+        assert_eq!(graph.edges.labelled("references").filter_map(|edge| edge.data.as_ref()).collect::<Vec<_>>(), vec![&Attribute::Int(1)]);
+        assert_eq!(graph.edge(&Handle::from(1), &Handle::from(13), "depends_on").unwrap().data, Some(Attribute::from("always")));
+
+        assert!(graph.disconnect(&Handle::from("top_button"), &Handle::from("hat"), "targets"));
+        assert!(!graph.disconnect(&Handle::from("top_button"), &Handle::from("hat"), "targets"));
+        assert_eq!(graph.edges.len(), 4);
+    }
+
+    #[test]
+    fn in_and_out() {
+        let graph = connected();
+        assert_eq!(graph.edges_to(&Handle::from("hat")).map(|edge| edge.from).collect::<Vec<_>>(), vec![6, 7, 8]);
+        assert_eq!(graph.edges_from(&Handle::from("hat")).count(), 0);
+        assert_eq!(graph.edges_from(&Handle::from("body")).map(|edge| edge.to).collect::<Vec<_>>(), vec![13]);
+        assert_eq!(graph.edges_from(&Handle::from("scarf")).count(), 0);
+
+        assert_eq!(names(graph.sources(&Handle::from("hat"), "references")), vec!["bottom_button", "middle_button", "top_button"]);
+        assert_eq!(names(graph.sources(&Handle::from("hat"), "targets")), Vec::<&str>::new());
+        assert_eq!(names(graph.targets(&Handle::from("middle_button"), "references")), vec!["hat"]);
+        assert_eq!(names(graph.targets(&Handle::from("body"), "depends_on")), vec!["arms"]);
+    }
+
+    /// Relationships of a scene, used as labels.
+    enum Relation {
+        References,
+        Targets,
+    }
+    impl AsRef<str> for Relation {
+        fn as_ref(&self) -> &str {
+            match self {
+                Relation::References => "references",
+                Relation::Targets => "targets",
+            }
+        }
+    }
+
+    #[test]
+    fn typed_labels() {
+        let mut graph = connected();
+        assert!(!graph.connect(&Handle::from("top_button"), &Handle::from("hat"), Relation::References));
+        assert!(graph.connect(&Handle::from("arms"), &Handle::from("hat"), Relation::Targets));
+        assert_eq!(names(graph.targets(&Handle::from("arms"), Relation::Targets)), vec!["hat"]);
+        assert_eq!(graph.edges.labelled(Relation::References).count(), 3);
+        assert_eq!(graph.edge(&Handle::from("arms"), &Handle::from("hat"), "targets").unwrap().label, "targets");
+    }
+
+    #[test]
+    fn remove_nodes() {
+        let mut graph = connected();
+        assert!(graph.connect(&Handle::from("hat"), &Handle::from("hat"), "targets"));
+        assert!(graph.remove(&Handle::from("mid")));
+        assert_eq!(graph.edges.iter().map(|edge| (edge.from, edge.to)).collect::<Vec<_>>(), vec![(1, 13), (12, 12)]);
+        assert!(graph.remove(&Handle::from("arms")));
+        assert!(graph.remove(&Handle::from("hat")));
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.edges.release(1), 0);
+    }
+
+    #[test]
+    fn many_edges() {
+        // A hub with many edges: lookups go through the node's own edges.
+        let mut edges = Edges::default();
+        for index in 1..20_000 {
+            assert!(edges.insert(Edge::new(0, index, "references")));
+            assert!(edges.insert(Edge::new(index, 0, "references")));
+        }
+        assert!(!edges.insert(Edge::new(0, 19_999, "references")));
+        assert_eq!(edges.outgoing(0).count(), 19_999);
+        assert_eq!(edges.incoming(7).map(|edge| edge.from).collect::<Vec<_>>(), vec![0]);
+
+        assert!(edges.remove(0, 7, "references").is_some());
+        assert_eq!(edges.incoming(7).count(), 0);
+        assert_eq!(edges.release(0), 39_997);
+        assert!(edges.is_empty());
+
+        // Equal when listing the same edges in order, whatever was removed before.
+        let mut other = Edges::default();
+        other.insert(Edge::new(1, 2, "a"));
+        other.insert(Edge::new(2, 3, "b"));
+        other.remove(1, 2, "a");
+        edges.insert(Edge::new(2, 3, "b"));
+        assert_eq!(edges, other);
+        edges.insert(Edge::new(1, 2, "a"));
+        other.insert(Edge::new(1, 2, "a"));
+        assert_eq!(edges.outgoing(2).chain(edges.outgoing(1)).count(), 2);
+        assert_eq!(edges, other);
+    }
+
+    #[test]
+    fn serialize() {
+        let graph = connected();
+        let json = serde_json::to_string(&graph).unwrap();
+        assert!(json.contains(r#""edges":[{"from":6,"to":12,"label":"references"},"#));
+        assert!(json.contains(r#"{"from":1,"to":13,"label":"depends_on","data":2.5}]"#));
+        let back: MachGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.edges, graph.edges);
+
+        // Graphs without edges don't write them.
+        assert!(!serde_json::to_string(&snowman()).unwrap().contains("edges"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod format {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Snowman with components, attributes, tags, edges, a removed node and a button prototype.
+    fn dressed() -> MachGraph {
+        let mut graph = snowman();
+        graph.name = String::from("snowman");
+        graph.push_component(&Handle::from("body"), 4);
+        graph.push_component(&Handle::from("body"), (2, 9));
+        graph.set_attribute(&Handle::from("body"), "color", "white");
+        graph.set_attribute(&Handle::from("hat"), "height", 0.25);
+        graph.set_attribute(&Handle::from("hat"), "sizes", vec![1, 2]);
+        graph.tag(&Handle::from("hat"), "selectable");
+        graph.tag(&Handle::from("mid"), "selectable");
+        graph.connect(&Handle::from("left"), &Handle::from("hat"), "references");
+        graph.connect_with(&Handle::from("body"), &Handle::from("arms"), "depends_on", true);
+        graph.remove(&Handle::from("top_button"));
+
+        let button = graph.create_prototype("button");
+        graph.edit_prototype(button, |prototype| {
+            let hole = prototype.push_child("hole");
+            prototype.push_component(&hole, 7);
+        });
+        graph.instantiate(button, &Handle::from("top"));
+        graph
+    }
+
+    /// Small graph with a payload.
+    fn weighted() -> MachGraph<f32> {
+        let mut graph = MachGraph::with_root("weights", 1.5);
+        let root = graph.root.clone();
+        let body = graph.push_child_with("body", &root, 2.0).unwrap();
+        graph.push_child_with("base", &body, 10.0);
+        graph
+    }
+
+    /// Load a file, expecting it to be rejected.
+    fn rejected(json: &str) -> String {
+        match MachGraph::<()>::from_json(json) {
+            Err(GraphError::Format(reason)) => reason,
+            other => panic!("file not rejected: {:?}", other.map(|graph| graph.name)),
+        }
+    }
+
+    #[test]
+    fn golden() {
+        assert_eq!(dressed().to_json_pretty().unwrap(), include_str!("data/snowman_v1.json"));
+        assert_eq!(weighted().to_json_pretty().unwrap(), include_str!("data/payload_v1.json"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = include_str!("data/snowman_v1.json");
+        let mut graph: MachGraph = MachGraph::from_json(json).unwrap();
+        assert_eq!(graph.to_json_pretty().unwrap(), json);
+        assert_eq!(MachGraph::<()>::from_json(&graph.to_json().unwrap()).unwrap().to_json_pretty().unwrap(), json);
+
+        // Parents, removed slots and handles are restored.
+        assert_eq!(graph.nodes.len(), 18);
+        assert!(graph.nodes[8].removed);
+        assert!(graph.get_node(&Handle::from(8)).is_none());
+        assert_eq!(graph.get_node(&Handle::from("hole")).unwrap().parent, 16);
+        assert!(!graph.get_root().unwrap().has_parent());
+        assert_eq!(graph.get_attribute(&Handle::from("hat"), "height"), Some(&Attribute::Float(0.25)));
+        assert_eq!(graph.tagged("selectable").count(), 2);
+        assert_eq!(graph.targets(&Handle::from("body"), "depends_on").count(), 1);
+        assert!(graph.topological_order().is_ok());
+
+        // Instances still follow their prototype.
+        graph.edit_prototype(0, |prototype| { prototype.push_child("rim"); });
+        assert_eq!(Handle::path(&graph, 18).unwrap(), "root.body.top.button.rim");
+
+        let weighted = MachGraph::<f32>::from_json(include_str!("data/payload_v1.json")).unwrap();
+        assert_eq!(weighted.nodes.iter().map(|node| node.data).collect::<Vec<f32>>(), vec![1.5, 2.0, 10.0]);
+    }
+
+    #[test]
+    fn shared_and_detached() {
+        // 'shared' is listed by 'a' and 'b' but its parent is 'b'. Pushed nodes aren't listed by any node.
+        let mut graph = MachGraph::new("g");
+        let a = graph.push_child("a");
+        let b = graph.push_child("b");
+        let shared = graph.push_child_of("shared", &b);
+        graph.get_node_mut(&a).unwrap().children.push(shared.index.unwrap());
+        let loose = graph.push(MachNode::new(String::from("loose")));
+        graph.get_node_mut(&Handle::from(loose)).unwrap().parent = loose;
+        graph.push(MachNode::new(String::from("pushed")));
+
+        let json = graph.to_json().unwrap();
+        assert!(json.contains(r#"{"name":"shared","parent":2}"#));
+        assert!(json.contains(r#"{"name":"loose"},{"name":"pushed","parent":0}"#));
+        let back = MachGraph::<()>::from_json(&json).unwrap();
+        assert_eq!(back.to_json().unwrap(), json);
+        let parents = |graph: &MachGraph| graph.nodes.iter().map(|node| node.parent).collect::<Vec<u32>>();
+        assert_eq!(parents(&back), parents(&graph));
+        assert_eq!(back.get_node(&Handle::from("a")).unwrap().children, vec![3]);
+
+        // Older files keep parents that the children lists don't say.
+        let old = r#"{"name":"g","root":{"path":"root","index":0},"nodes":[
+            {"name":"root","parent":0,"index":0,"children":[1,2],"components":[]},
+            {"name":"a","parent":0,"index":1,"children":[3],"components":[]},
+            {"name":"b","parent":0,"index":2,"children":[3],"components":[]},
+            {"name":"shared","parent":2,"index":3,"children":[],"components":[]}]}"#;
+        assert_eq!(parents(&MachGraph::<()>::from_json(old).unwrap()), vec![0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn versions() {
+        assert!(MachGraph::<()>::from_json(r#"{"name":"g","root":0,"nodes":[{"name":"root"}]}"#).is_ok());
+        assert_eq!(rejected(r#"{"version":"one","name":"g","root":0,"nodes":[{"name":"root"}]}"#), "bad format version \"one\"");
+        match MachGraph::<()>::from_json(r#"{"version":2,"name":"g","root":0,"nodes":[]}"#) {
+            Err(error) => assert_eq!(error.to_string(), "unsupported graph file version 2"),
+            Ok(_) => panic!("future version loaded"),
+        }
+        assert!(MachGraph::<()>::from_json(r#"{"version":1,"name":"g","root":0,"nodes":[{"name":"root"}]}"#).is_ok());
+    }
+
+    #[test]
+    fn validation() {
+        let file = |nodes: &str, rest: &str| format!(r#"{{"version":1,"name":"g","root":0,"nodes":[{}]{}}}"#, nodes, rest);
+        assert!(rejected("{").starts_with("EOF while parsing"));
+        assert!(rejected(&file(r#"{"children":[1]}"#, "")).starts_with("missing field `name`"));
+        assert_eq!(rejected(&file("null", "")), "root 0 is not a node");
+        assert_eq!(rejected(&file(r#"{"name":"root","children":[1]}"#, "")), "node 0 has child 1 which is not a node");
+        assert_eq!(rejected(&file(r#"{"name":"root"},{"name":"a","parent":2}"#, "")), "node 1 has parent 2 which is not a node");
+        assert_eq!(rejected(&file(r#"{"name":"root"},null"#, r#","tags":{"red":[1]}"#)), "tag 'red' is on 1 which is not a node");
+        assert_eq!(rejected(&file(r#"{"name":"root"}"#, r#","edges":[{"from":0,"to":3,"label":"targets"}]"#)), "edge 'targets' from 0 to 3 is not between nodes");
+        assert_eq!(rejected(&file(r#"{"name":"root"}"#, r#","instances":[{"prototype":0,"root":0,"nodes":[0]}]"#)), "instance of prototype 0 which doesn't exist");
+
+        // Children must not form a cycle, on load or when writing.
+        let cycle = file(r#"{"name":"root","children":[1]},{"name":"a","children":[2]},{"name":"b","children":[1]}"#, "");
+        let error = MachGraph::<()>::from_json(&cycle).unwrap_err();
+        assert_eq!(error.to_string(), "graph contains a cycle: a[1] -> b[2]");
+        let mut graph = MachGraph::new("g");
+        let a = graph.push_child("a");
+        let b = graph.push_child_of("b", &a);
+        graph.get_node_mut(&b).unwrap().children.push(a.index.unwrap());
+        assert!(matches!(graph.to_json(), Err(GraphError::Cycle(_))));
+
+        let json = include_str!("data/snowman_v1.json");
+        assert_eq!(rejected(&json.replace("        16,\n        17\n", "        16\n")), "instance at 16 doesn't match prototype 0");
+        assert_eq!(rejected(&json.replacen("      \"synced\": [\n        {\n          \"name\": \"button\",\n          \"components\": []\n        },\n", "      \"synced\": [\n", 1)), "prototype 'button' has 2 nodes but 1 synced");
+    }
+}
\ No newline at end of file
@@ -147,4 +147,232 @@ mod mach_graph_test {
             }
         });
     }
+
+    #[test]
+    fn fold_up() {
+        graph!(graph, {
+            node!(graph, left, "left", {
+                node!(graph, _left_a, "left_a", left);
+                node!(graph, _left_b, "left_b", left);
+            });
+            node!(graph, _right, "right");
+        });
+        let orphan = graph.push(MachNode::new(String::from("orphan")));
+
+        let sizes = graph.fold_up(|_| 1, |_, size, child| size + child).unwrap();
+        assert_eq!(sizes.len(), 6);
+        assert_eq!(sizes[0], Some(5));
+        assert_eq!(sizes[1], Some(3));
+        assert_eq!(sizes[2], Some(1));
+        assert_eq!(sizes[4], Some(1));
+        assert_eq!(sizes[orphan as usize], None);
+
+        let names = graph.fold_up(|node| node.name.clone(), |_, names, child| format!("{}({})", names, child)).unwrap();
+        assert_eq!(names[0].as_deref(), Some("root(left(left_a)(left_b))(right)"));
+
+        let sizes = graph.fold_up_from(&Handle::from("left"), |_| 1, |_, size, child| size + child).unwrap();
+        assert_eq!(sizes[1], Some(3));
+        assert_eq!(sizes[0], None);
+        assert_eq!(sizes[4], None);
+    }
+
+    #[test]
+    fn propagate_down() {
+        graph!(graph, {
+            node!(graph, left, "left", {
+                node!(graph, left_a, "left_a", left, {
+                    node!(graph, _left_a_a, "left_a_a", left_a);
+                });
+                node!(graph, _hidden, "hidden", left);
+            });
+            node!(graph, _right, "right");
+        });
+
+        let depths = graph.propagate_down(0, |depth, node| {
+            if node.has_parent() { Some(depth + 1) } else { Some(*depth) }
+        }).unwrap();
+        assert_eq!(depths, vec![Some(0), Some(1), Some(2), Some(3), Some(2), Some(1)]);
+
+        let paths = graph.propagate_down(String::new(), |path, node| {
+            if node.name == "hidden" { return None; }
+            if path.is_empty() { Some(node.name.clone()) } else { Some(format!("{}.{}", path, node.name)) }
+        }).unwrap();
+        assert_eq!(paths[3].as_deref(), Some("root.left.left_a.left_a_a"));
+        assert_eq!(paths[4], None);
+        assert_eq!(paths[5].as_deref(), Some("root.right"));
+
+        let stopped = graph.propagate_down_from(&Handle::from("left"), 1, |value, node| {
+            if node.name == "left_a" { None } else { Some(value * 2) }
+        }).unwrap();
+        assert_eq!(stopped, vec![None, Some(2), None, None, Some(4), None]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut graph = MachGraph::default();
+        let left = graph.push_child("left");
+        let left_a = graph.push_child_of("left_a", &left);
+        graph.push_child_of("left_b", &left);
+        let right = graph.push_child("right");
+
+        assert!(!graph.remove(&graph.root.clone()));
+        assert!(graph.remove(&left));
+        assert_eq!(graph.nodes.len(), 5);
+        assert!(graph.get_node(&left).is_none());
+        assert!(graph.get_node(&left_a).is_none());
+        assert!(graph.get_node(&Handle::from("left_b")).is_none());
+        assert_eq!(graph.get_root().unwrap().children, vec![4]);
+        assert_eq!(graph.get_node(&right).unwrap().index, 4);
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 2);
+        assert!(!graph.remove(&left));
+    }
+
+    #[test]
+    fn reparent() {
+        let mut graph = MachGraph::default();
+        let left = graph.push_child("left");
+        let left_a = graph.push_child_of("left_a", &left);
+        let right = graph.push_child("right");
+
+        assert!(graph.reparent(&left_a, &right));
+        assert!(graph.get_node(&left).unwrap().children.is_empty());
+        assert_eq!(graph.get_node(&right).unwrap().children, vec![2]);
+        assert_eq!(graph.get_node(&left_a).unwrap().parent, 3);
+        assert_eq!(Handle::path(&graph, 2).unwrap(), "root.right.left_a");
+
+        assert!(!graph.reparent(&right, &left_a));
+        assert!(!graph.reparent(&right, &right));
+        assert!(!graph.reparent(&graph.root.clone(), &left));
+        assert!(graph.reparent(&right, &left));
+        assert_eq!(Handle::path(&graph, 2).unwrap(), "root.left.right.left_a");
+    }
+
+    #[test]
+    fn component_types() {
+        const MESH: u32 = 1;
+        const LIGHT: u32 = 2;
+        let mut graph = MachGraph::default();
+        let lamp = graph.push_child("lamp");
+        graph.push_component(&lamp, (MESH, 0));
+        graph.push_component(&lamp, (LIGHT, 0));
+        graph.push_component(&lamp, ComponentRef::new(MESH, 3));
+        graph.push_component(&lamp, 7);
+
+        let node = graph.get_node(&lamp).unwrap();
+        assert!(node.has_component(MESH));
+        assert!(node.has_component(ComponentRef::UNTYPED));
+        assert!(!node.has_component(3));
+        assert_eq!(graph.components_of_type(&lamp, MESH).collect::<Vec<u32>>(), vec![0, 3]);
+        assert_eq!(graph.components_of_type(&lamp, LIGHT).collect::<Vec<u32>>(), vec![0]);
+        assert_eq!(graph.components_of_type(&Handle::from("missing"), MESH).count(), 0);
+    }
+
+    #[test]
+    fn component_lifecycle() {
+        let mut graph = MachGraph::default();
+        let lamp = graph.push_child("lamp");
+        assert!(graph.push_component(&lamp, 1));
+        assert!(graph.push_component(&lamp, (2, 1)));
+        assert!(graph.push_component(&lamp, 3));
+        assert!(!graph.push_component(&lamp, 1));
+        assert!(!graph.push_component(&Handle::from("missing"), 1));
+        assert!(graph.contains_component(&lamp, (2, 1)));
+        assert!(!graph.contains_component(&lamp, 2));
+
+        assert!(graph.replace_component(&lamp, 1, 4));
+        assert!(!graph.replace_component(&lamp, 1, 5));
+        assert!(!graph.replace_component(&lamp, 4, 3));
+        assert_eq!(graph.get_node(&lamp).unwrap().components, vec![ComponentRef::from(4), ComponentRef::new(2, 1), ComponentRef::from(3)]);
+
+        // Duplicates are kept once, at their first position.
+        assert!(graph.set_components(&lamp, vec![ComponentRef::from(3), ComponentRef::from(4), ComponentRef::from(3), ComponentRef::new(2, 1)]));
+        assert_eq!(graph.get_node(&lamp).unwrap().components, vec![ComponentRef::from(3), ComponentRef::from(4), ComponentRef::new(2, 1)]);
+        assert!(!graph.set_components(&Handle::from("missing"), Vec::new()));
+
+        assert!(graph.remove_component(&lamp, 4));
+        assert!(!graph.remove_component(&lamp, 4));
+        assert_eq!(graph.clear_components(&lamp), vec![ComponentRef::from(3), ComponentRef::new(2, 1)]);
+        assert!(!graph.get_node(&lamp).unwrap().has_components());
+    }
+
+    #[test]
+    fn component_hooks() {
+        use std::sync::{Arc, Mutex};
+        const LIGHT: u32 = 2;
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut graph = MachGraph::default();
+        let added = log.clone();
+        graph.on_add(LIGHT, move |node, component| added.lock().unwrap().push(format!("+{}:{}", node.name, component.index)));
+        let removed = log.clone();
+        graph.on_remove(LIGHT, move |node, component| removed.lock().unwrap().push(format!("-{}:{}", node.name, component.index)));
+
+        let lamp = graph.push_child("lamp");
+        let bulb = graph.push_child_of("bulb", &lamp);
+        graph.push_component(&lamp, (LIGHT, 0));
+        graph.push_component(&lamp, 5);
+        graph.push_component(&lamp, (LIGHT, 0));
+        graph.push_component(&bulb, (LIGHT, 1));
+        graph.replace_component(&lamp, (LIGHT, 0), (LIGHT, 2));
+        graph.remove_component(&lamp, 5);
+        graph.remove(&lamp);
+
+        assert_eq!(*log.lock().unwrap(), vec!["+lamp:0", "+bulb:1", "-lamp:0", "+lamp:2", "-lamp:2", "-bulb:1"]);
+    }
+
+    #[test]
+    fn component_ref_counts() {
+        let mut graph = MachGraph::default();
+        let lamp = graph.push_child("lamp");
+        let bulb = graph.push_child_of("bulb", &lamp);
+        let desk = graph.push_child("desk");
+        graph.push_component(&lamp, (1, 0));
+        graph.push_component(&bulb, (1, 0));
+        graph.push_component(&bulb, 7);
+        graph.push_component(&desk, 7);
+        graph.push_component(&desk, 8);
+        assert_eq!(graph.ref_count((1, 0)), 2);
+        assert_eq!(graph.ref_count(7), 2);
+        assert_eq!(graph.ref_count(9), 0);
+
+        graph.remove_component(&desk, 7);
+        graph.replace_component(&desk, 8, 9);
+        assert_eq!(graph.collect_garbage(), vec![ComponentRef::from(8)]);
+        assert!(graph.collect_garbage().is_empty());
+
+        graph.remove(&lamp);
+        assert_eq!(graph.ref_count(7), 0);
+        assert_eq!(graph.collect_garbage(), vec![ComponentRef::from(7), ComponentRef::new(1, 0)]);
+        assert_eq!(graph.ref_counts().len(), 1);
+
+        // Released and then referenced again: not garbage.
+        graph.remove_component(&desk, 9);
+        graph.push_component(&desk, 9);
+        assert!(graph.collect_garbage().is_empty());
+    }
+
+    #[test]
+    fn component_ref_counts_after_load() {
+        let mut graph = MachGraph::default();
+        let lamp = graph.push_child("lamp");
+        let desk = graph.push_child("desk");
+        graph.get_node_mut(&lamp).unwrap().components.push(ComponentRef::from(3));
+        graph.get_node_mut(&desk).unwrap().components.push(ComponentRef::from(3));
+        assert_eq!(graph.ref_count(3), 2);
+
+        graph.remove(&lamp);
+        assert_eq!(graph.ref_count(3), 1);
+        graph.get_node_mut(&desk).unwrap().components.clear();
+        graph.recount_components();
+        assert_eq!(graph.ref_count(3), 0);
+        assert!(graph.collect_garbage().is_empty());
+
+        // Pushed nodes count once counts are built.
+        let mut lamp = MachNode::new(String::from("lamp"));
+        lamp.components.push(ComponentRef::from(5));
+        graph.push(lamp);
+        let mut bulb = MachNode::new(String::from("bulb"));
+        bulb.components.push(ComponentRef::from(5));
+        graph.push(bulb);
+        assert_eq!(graph.ref_count(5), 2);
+    }
 }
\ No newline at end of file
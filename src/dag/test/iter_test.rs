@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod iter {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    fn names<'a>(nodes: impl Iterator<Item = &'a MachNode>) -> Vec<String> {
+        nodes.map(|node| node.name.clone()).collect()
+    }
+
+    #[test]
+    fn ancestors() {
+        let graph = snowman();
+        assert_eq!(names(graph.ancestors(&Handle::from("middle_button"))), vec!["mid", "body", "root"]);
+        assert_eq!(names(graph.ancestors(&Handle::from("root"))).len(), 0);
+        assert_eq!(names(graph.ancestors(&Handle::from("missing"))).len(), 0);
+    }
+
+    #[test]
+    fn descendants() {
+        let graph = snowman();
+        assert_eq!(names(graph.descendants(&Handle::from("arms"))), vec!["left", "right"]);
+        assert_eq!(names(graph.descendants(&Handle::from("body")))[..4], ["base", "left", "right", "mid"]);
+        assert_eq!(graph.descendants(&graph.root.clone()).count(), 15);
+        assert_eq!(graph.descendants(&Handle::from("hat")).count(), 0);
+    }
+
+    #[test]
+    fn siblings() {
+        let graph = snowman();
+        assert_eq!(names(graph.siblings(&Handle::from("mid"))), vec!["base", "top"]);
+        assert_eq!(names(graph.siblings(&Handle::from("hat"))), vec!["body", "arms"]);
+        assert_eq!(graph.siblings(&Handle::from("root")).count(), 0);
+    }
+
+    #[test]
+    fn leaves() {
+        let graph = snowman();
+        let leaves: Vec<u32> = graph.leaves().map(|node| node.index).collect();
+        assert_eq!(leaves, vec![3, 4, 6, 7, 8, 10, 11, 12, 14, 15]);
+    }
+
+    #[test]
+    fn removed_nodes() {
+        // Children lists that still name removed nodes don't lead into them.
+        let mut graph = snowman();
+        graph.nodes[2].removed = true;
+        graph.nodes[12].removed = true;
+        assert_eq!(names(graph.descendants(&Handle::from(1)))[..3], ["mid", "bottom_button", "middle_button"]);
+        assert_eq!(graph.descendants(&Handle::from(2)).count(), 0);
+        assert_eq!(names(graph.siblings(&Handle::from("body"))), vec!["arms"]);
+        graph.nodes[14].removed = true;
+        graph.nodes[15].removed = true;
+        let leaves: Vec<u32> = graph.leaves().map(|node| node.index).collect();
+        assert_eq!(leaves, vec![6, 7, 8, 10, 11, 13]);
+    }
+
+    #[test]
+    fn depth_and_size() {
+        let graph = snowman();
+        assert_eq!(graph.depth(&Handle::from("root")), Some(0));
+        assert_eq!(graph.depth(&Handle::from("root.body.top.left")), Some(3));
+        assert_eq!(graph.depth(&Handle::from("missing")), None);
+        assert_eq!(graph.subtree_size(&Handle::from("root")), 16);
+        assert_eq!(graph.subtree_size(&Handle::from("mid")), 4);
+        assert_eq!(graph.subtree_size(&Handle::from("hat")), 1);
+        assert_eq!(graph.subtree_size(&Handle::from("missing")), 0);
+    }
+
+    #[test]
+    fn ancestry() {
+        let graph = snowman();
+        assert!(graph.is_ancestor_of(&Handle::from("body"), &Handle::from("top_button")));
+        assert!(graph.is_ancestor_of(&Handle::from("root"), &Handle::from("arms.left")));
+        assert!(!graph.is_ancestor_of(&Handle::from("arms"), &Handle::from("top_button")));
+        assert!(!graph.is_ancestor_of(&Handle::from("mid"), &Handle::from("mid")));
+
+        let lca = graph.lowest_common_ancestor(&Handle::from("top_button"), &Handle::from("root.body.top.left"));
+        assert_eq!(lca.map(|node| node.index), Some(1));
+        let lca = graph.lowest_common_ancestor(&Handle::from("mid"), &Handle::from("bottom_button"));
+        assert_eq!(lca.map(|node| node.index), Some(5));
+        let lca = graph.lowest_common_ancestor(&Handle::from("hat"), &Handle::from("arms.right"));
+        assert_eq!(lca.map(|node| node.index), Some(0));
+        assert!(graph.lowest_common_ancestor(&Handle::from("hat"), &Handle::from("missing")).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod migrate {
+    use crate::dag::*;
+
+    fn rejected(json: &str) -> GraphError {
+        match MachGraph::<()>::from_json(json) {
+            Err(error) => error,
+            Ok(graph) => panic!("file not rejected: {}", graph.name),
+        }
+    }
+
+    #[test]
+    fn legacy_files() {
+        // Files written by deriving Serialize, before the format was versioned.
+        let graph: MachGraph = MachGraph::from_json(include_str!("data/snowman_v0.json")).unwrap();
+        assert_eq!(graph.to_json_pretty().unwrap(), include_str!("data/snowman_v1.json"));
+        assert!(graph.nodes[8].removed);
+        assert_eq!(graph.get_node(&Handle::from("hole")).unwrap().parent, 16);
+
+        let json = "{\"name\":\"old\",\"index\":0,\"root\":{\"path\":\"root\",\"index\":0},\"nodes\":[\
+            {\"name\":\"root\",\"parent\":0,\"index\":0,\"children\":[1],\"components\":[]},\
+            {\"name\":\"lamp\",\"parent\":0,\"index\":1,\"children\":[],\"components\":[3,5]}]}";
+        let graph: MachGraph = MachGraph::from_json(json).unwrap();
+        assert_eq!(graph.to_json().unwrap(), "{\"version\":1,\"name\":\"old\",\"index\":0,\"root\":0,\"nodes\":[\
+            {\"name\":\"root\",\"children\":[1]},{\"name\":\"lamp\",\"components\":[3,5]}]}");
+
+        let mut weighted = MachGraph::with_root("weights", 1.5);
+        weighted.push_child("body");
+        let graph = MachGraph::<f32>::from_json(&serde_json::to_string(&weighted).unwrap()).unwrap();
+        assert_eq!(graph.to_json().unwrap(), weighted.to_json().unwrap());
+    }
+
+    #[test]
+    fn bad_legacy_files() {
+        let error = rejected(r#"{"name":"old","index":0,"root":{"path":"root","index":null},"nodes":[]}"#);
+        assert_eq!(error.to_string(), "invalid graph file: version 0 file: root has no index");
+        let error = rejected(r#"{"name":"old","index":0,"root":{"path":"root","index":0},"nodes":[{"name":"root","parent":0,"index":0,"children":[3],"components":[]}]}"#);
+        assert_eq!(error.to_string(), "invalid graph file: node 0 has child 3 which is not a node");
+    }
+
+    #[test]
+    fn future_files() {
+        let future = format!(r#"{{"version":{},"name":"g","root":0,"nodes":[{{"name":"root"}}],"layers":[]}}"#, FORMAT_VERSION + 1);
+        match rejected(&future) {
+            GraphError::UnsupportedVersion(version) => assert_eq!(version, FORMAT_VERSION + 1),
+            other => panic!("future file not reported: {}", other),
+        }
+        assert_eq!(rejected(r#"{"version":7,"name":"g","root":0,"nodes":[]}"#).to_string(), "unsupported graph file version 7");
+        assert_eq!(rejected(r#"{"version":-1,"name":"g","root":0,"nodes":[]}"#).to_string(), "invalid graph file: bad format version -1");
+    }
+}
\ No newline at end of file
@@ -1,4 +1,54 @@
 pub mod graph_test;
 pub mod handle_test;
 pub mod visitor_test;
-pub mod serde_test;
\ No newline at end of file
+pub mod serde_test;
+pub mod parallel_test;
+pub mod iter_test;
+pub mod view_test;
+pub mod commands_test;
+pub mod combinators_test;
+pub mod topology_test;
+pub mod store_test;
+pub mod query_test;
+pub mod prototype_test;
+pub mod attribute_test;
+pub mod tags_test;
+pub mod payload_test;
+pub mod edges_test;
+pub mod format_test;
+pub mod migrate_test;
+pub mod binary_test;
+
+
+#[cfg(test)]
+use crate::dag::*;
+
+
+/// The snowman graph most tests run on:
+/// root0, body1, base2, left3, right4, mid5, bottom_button6, middle_button7, top_button8, top9, left10, right11, hat12, arms13, left14, right15.
+#[cfg(test)]
+pub fn snowman() -> MachGraph {
+    graph!(graph, {
+        node!(graph, body, "body", {
+            node!(graph, base, "base", body, {
+                node!(graph, _left_foot, "left", base);
+                node!(graph, _right_foot, "right", base);
+            });
+            node!(graph, mid, "mid", body, {
+                node!(graph, _bottom, "bottom_button", mid);
+                node!(graph, _middle, "middle_button", mid);
+                node!(graph, _top, "top_button", mid);
+            });
+            node!(graph, top, "top", body, {
+                node!(graph, _left_eye, "left", top);
+                node!(graph, _right_eye, "right", top);
+            });
+        });
+        node!(graph, _hat, "hat");
+        node!(graph, arms, "arms", {
+            node!(graph, _left_arm, "left", arms);
+            node!(graph, _right_arm, "right", arms);
+        });
+    });
+    graph
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+#[cfg(all(test, feature = "parallel"))]
+mod parallel {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::dag::*;
+
+    #[derive(Default)]
+    struct CountVisitor {
+        pub count: AtomicUsize,
+    }
+    impl Visitor for CountVisitor {
+        fn visit(&self, _node: &MachNode) {
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[derive(Default)]
+    struct OrderVisitor {
+        pub order: Mutex<Vec<u32>>,
+    }
+    impl Visitor for OrderVisitor {
+        fn visit(&self, node: &MachNode) {
+            self.order.lock().unwrap().push(node.index);
+        }
+    }
+    impl OrderVisitor {
+        fn position(&self, index: u32) -> usize {
+            self.order.lock().unwrap().iter().position(|i| *i == index).expect("not visited")
+        }
+    }
+
+    fn wide_graph() -> MachGraph {
+        let mut graph = MachGraph::default();
+        for i in 0..20 {
+            let branch = graph.push_child(&format!("branch_{}", i));
+            for j in 0..20 {
+                let leaf = graph.push_child_of(&format!("leaf_{}", j), &branch);
+                graph.push_child_of("tip", &leaf);
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn par_visit_all() {
+        let graph = wide_graph();
+        let visitor = CountVisitor::default();
+        graph.par_visit_all(&visitor);
+        assert_eq!(visitor.count.load(Ordering::SeqCst), graph.nodes.len());
+
+        let indices = graph.par_map_all(|node| node.index);
+        assert_eq!(indices, (0..graph.nodes.len() as u32).map(Some).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn combinators() {
+        let graph = wide_graph();
+        let mut indices = Collect::indices();
+        let mut counts = CountByDepth::default();
+        graph.par_pre_visit(&(&mut indices).chain(&mut counts)).unwrap();
+        let mut indices = indices.into_items();
+        indices.sort();
+        assert_eq!(indices, (0..graph.nodes.len() as u32).collect::<Vec<u32>>());
+        assert_eq!(counts.counts(), vec![1, 20, 400, 400]);
+    }
+
+    #[test]
+    fn par_pre_visit() {
+        let graph = wide_graph();
+        let visitor = OrderVisitor::default();
+        graph.par_pre_visit(&visitor).unwrap();
+        assert_eq!(visitor.order.lock().unwrap().len(), graph.nodes.len());
+        for node in graph.nodes.iter().skip(1) {
+            assert!(visitor.position(node.parent) < visitor.position(node.index));
+        }
+    }
+
+    #[test]
+    fn par_post_visit() {
+        let graph = wide_graph();
+        let visitor = OrderVisitor::default();
+        graph.par_post_visit(&visitor).unwrap();
+        assert_eq!(visitor.order.lock().unwrap().len(), graph.nodes.len());
+        for node in graph.nodes.iter().skip(1) {
+            assert!(visitor.position(node.parent) > visitor.position(node.index));
+        }
+    }
+
+    #[test]
+    fn par_map_subtree() {
+        let graph = wide_graph();
+        let names = graph.par_map_subtree(&Handle::from("branch_3"), |node| node.name.clone()).unwrap();
+        assert_eq!(names.len(), 41);
+        assert_eq!(names[0], "branch_3");
+        assert_eq!(names[1], "leaf_0");
+        assert_eq!(names[2], "tip");
+        assert_eq!(names[3], "leaf_1");
+        assert_eq!(names[40], "tip");
+
+        let all = graph.par_map_subtree(&graph.root.clone(), |node| node.index).unwrap();
+        let mut expected = Vec::new();
+        let mut stack = vec![0u32];
+        while let Some(index) = stack.pop() {
+            expected.push(index);
+            for child in graph.nodes[index as usize].children.iter().rev() { stack.push(*child); }
+        }
+        assert_eq!(all, expected);
+    }
+
+    /// A single chain, deeper than the worker stacks could recurse.
+    fn deep_chain(depth: u32) -> MachGraph {
+        let mut graph = MachGraph::default();
+        for index in 1..depth {
+            let mut node = MachNode::new(format!("n{}", index));
+            node.parent = index - 1;
+            graph.push(node);
+            graph.nodes[index as usize - 1].children.push(index);
+        }
+        graph
+    }
+
+    #[test]
+    fn deep_graphs() {
+        let graph = deep_chain(200_000);
+        let visitor = OrderVisitor::default();
+        graph.par_pre_visit(&visitor).unwrap();
+        assert_eq!(*visitor.order.lock().unwrap(), (0..200_000).collect::<Vec<u32>>());
+
+        let visitor = OrderVisitor::default();
+        graph.par_post_visit(&visitor).unwrap();
+        assert_eq!(*visitor.order.lock().unwrap(), (0..200_000).rev().collect::<Vec<u32>>());
+
+        let indices = graph.par_map_subtree(&Handle::from(10), |node| node.index).unwrap();
+        assert_eq!(indices, (10..200_000).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn shared_children() {
+        // Stacked diamonds, visited once each and deeper than the parallel split.
+        let mut graph = MachGraph::default();
+        let mut bottom = graph.root.clone();
+        for _ in 0..40 {
+            let left = graph.push_child_of("left", &bottom);
+            let right = graph.push_child_of("right", &bottom);
+            bottom = graph.push_child_of("bottom", &left);
+            graph.get_node_mut(&right).unwrap().children.push(bottom.index.unwrap());
+        }
+
+        let visitor = CountVisitor::default();
+        graph.par_pre_visit(&visitor).unwrap();
+        assert_eq!(visitor.count.load(Ordering::SeqCst), graph.nodes.len());
+
+        let visitor = CountVisitor::default();
+        graph.par_post_visit(&visitor).unwrap();
+        assert_eq!(visitor.count.load(Ordering::SeqCst), graph.nodes.len());
+
+        let names = Collect::names();
+        graph.pre_visit(&names).unwrap();
+        let parallel = graph.par_map_subtree(&graph.root.clone(), |node| node.name.clone()).unwrap();
+        assert_eq!(parallel, *names.items());
+    }
+
+    #[test]
+    fn removed_nodes() {
+        let mut graph = wide_graph();
+        assert!(graph.remove(&Handle::from("branch_0")));
+        let indices = graph.par_map_all(|node| node.index);
+        assert_eq!(indices.len(), graph.nodes.len());
+        assert_eq!(indices.iter().flatten().count(), graph.nodes.len() - 41);
+        assert_eq!((indices[1], indices[42]), (None, Some(42)));
+
+        let visitor = CountVisitor::default();
+        graph.par_pre_visit(&visitor).unwrap();
+        assert_eq!(visitor.count.load(Ordering::SeqCst), graph.nodes.len() - 41);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod payload {
+    use serde::{Serialize, Deserialize};
+    use crate::dag::*;
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    struct Weight {
+        kg: f32,
+    }
+
+    /// Snowman where every part has a weight.
+    fn snowman() -> MachGraph<Weight> {
+        let mut graph = MachGraph::with_root("snowman", Weight { kg: 0.0 });
+        let root = graph.root.clone();
+        let body = graph.push_child_with("body", &root, Weight { kg: 2.0 }).unwrap();
+        graph.push_child_with("base", &body, Weight { kg: 10.0 });
+        let mid = graph.push_child_with("mid", &body, Weight { kg: 6.0 }).unwrap();
+        graph.push_child_with("button", &mid, Weight { kg: 0.5 });
+        graph.push_child_with("top", &body, Weight { kg: 3.0 });
+        graph.push_child_of("hat", &root);
+        graph
+    }
+
+    /// Sums the weight of visited nodes and makes every node heavier.
+    #[derive(Default)]
+    struct Scale {
+        total: f32,
+    }
+    impl Visitor<Weight> for Scale {
+        fn visit_mut(&mut self, node: &mut MachNode<Weight>) {
+            self.total += node.data.kg;
+            node.data.kg *= 2.0;
+        }
+    }
+
+    #[test]
+    fn data() {
+        let mut graph = snowman();
+        assert_eq!(graph.get_data(&Handle::from("base")), Some(&Weight { kg: 10.0 }));
+        assert_eq!(graph.get_data(&Handle::from("hat")), Some(&Weight::default()));
+        graph.get_data_mut(&Handle::from("hat")).unwrap().kg = 1.0;
+        assert_eq!(graph.get_node(&Handle::from("hat")).unwrap().data.kg, 1.0);
+        assert_eq!(Handle::path(&graph, 4).unwrap(), "root.body.mid.button");
+    }
+
+    #[test]
+    fn visitors() {
+        let mut graph = snowman();
+        let mut scale = Scale::default();
+        graph.pre_visit_mut(&mut scale).unwrap();
+        assert_eq!(scale.total, 21.5);
+
+        // Weights were doubled, so the body (4kg) is left out.
+        let heavy = Collect::names();
+        graph.pre_visit_filtered(|node| node.data.kg >= 6.0, |_| true, &heavy).unwrap();
+        assert_eq!(*heavy.items(), vec!["base", "mid", "top"]);
+        let light = Collect::indices().filter(|node: &MachNode<Weight>| node.data.kg < 1.0);
+        graph.pre_visit(&light).unwrap();
+        assert_eq!(*light.inner.items(), vec![0, 6]);
+
+        let totals = graph.fold_up(|node| node.data.kg, |node, total, child| total.max(node.data.kg) + child).unwrap();
+        assert_eq!(totals[1], Some(43.0));
+
+        let mut counts = CountByDepth::default();
+        graph.post_visit_mut(&mut counts).unwrap();
+        assert_eq!(counts.counts(), vec![1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn serialize() {
+        let graph = snowman();
+        let json = serde_json::to_string(&graph).unwrap();
+        assert!(json.contains("\"name\":\"base\",\"parent\":1,\"index\":2,\"children\":[],\"components\":[],\"data\":{\"kg\":10.0}"));
+        let back: MachGraph<Weight> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.get_data(&Handle::from("button")), Some(&Weight { kg: 0.5 }));
+
+        // Without a payload nothing changes, and payload graphs load files without one.
+        let plain = serde_json::to_string(&MachGraph::default()).unwrap();
+        assert!(!plain.contains("data"));
+        let loaded: MachGraph<Weight> = serde_json::from_str(&plain).unwrap();
+        assert_eq!(loaded.get_root().unwrap().data, Weight::default());
+    }
+
+    #[test]
+    fn components_and_prototypes() {
+        let mut graph = snowman();
+        graph.store.insert(2, String::from("snow"));
+        let found: Vec<(f32, &String)> = graph.query::<&String>().iter().map(|(node, name)| (node.data.kg, name)).collect();
+        assert_eq!(found, vec![(10.0, &String::from("snow"))]);
+
+        let button = graph.prototype_from(&Handle::from("button")).unwrap();
+        let copy = graph.instantiate(button, &Handle::from("top")).unwrap();
+        assert_eq!(graph.get_data(&copy), Some(&Weight::default()));
+        assert_eq!(graph.topological_order().unwrap().len(), 8);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod prototype {
+    use crate::dag::*;
+
+    const MESH: u32 = 1;
+    const COLOR: u32 = 2;
+
+    /// Snowman body with two arms made from an "arm" prototype (arm -> hand -> thumb).
+    fn snowman() -> (MachGraph, u32) {
+        let mut graph = MachGraph::default();
+        let arm = graph.create_prototype("arm");
+        graph.edit_prototype(arm, |prototype| {
+            let root = prototype.root.clone();
+            prototype.push_component(&root, (MESH, 0));
+            let hand = prototype.push_child("hand");
+            prototype.push_component(&hand, (MESH, 1));
+            prototype.push_child_of("thumb", &hand);
+        });
+        let body = graph.push_child("body");
+        graph.instantiate(arm, &body);
+        graph.instantiate(arm, &body);
+        (graph, arm)
+    }
+
+    fn components(graph: &MachGraph, index: u32) -> Vec<ComponentRef> {
+        graph.get_node(&Handle::from(index)).unwrap().components.clone()
+    }
+
+    #[test]
+    fn instantiate() {
+        let (graph, arm) = snowman();
+        assert_eq!(graph.instances.len(), 2);
+        assert_eq!(graph.nodes.len(), 8);
+        assert_eq!(Handle::path(&graph, 4).unwrap(), "root.body.arm.hand.thumb");
+        assert_eq!(Handle::path(&graph, 7).unwrap(), "root.body.arm.hand.thumb");
+        assert_eq!(components(&graph, 6), vec![ComponentRef::new(MESH, 1)]);
+        assert_eq!(graph.ref_count((MESH, 1)), 2);
+
+        let (prototype, root) = graph.instance_of(&Handle::from(7)).unwrap();
+        assert_eq!((prototype, root.index), (arm, Some(5)));
+        assert!(graph.instance_of(&Handle::from("body")).is_none());
+        assert!(!graph.is_overridden(&Handle::from(6)));
+    }
+
+    #[test]
+    fn propagate() {
+        let (mut graph, arm) = snowman();
+
+        // Override the first arm's hand and rename the second arm's thumb.
+        graph.push_component(&Handle::from(3), (COLOR, 9));
+        graph.get_node_mut(&Handle::from(7)).unwrap().name = String::from("pinky");
+        assert!(graph.is_overridden(&Handle::from(3)));
+
+        graph.edit_prototype(arm, |prototype| {
+            let hand = Handle::from("hand");
+            prototype.replace_component(&hand, (MESH, 1), (MESH, 2));
+            prototype.get_node_mut(&Handle::from("thumb")).unwrap().name = String::from("finger");
+            prototype.push_child_of("glove", &hand);
+        });
+
+        assert_eq!(components(&graph, 3), vec![ComponentRef::new(MESH, 1), ComponentRef::new(COLOR, 9)]);
+        assert_eq!(components(&graph, 6), vec![ComponentRef::new(MESH, 2)]);
+        assert_eq!(graph.get_node(&Handle::from(4)).unwrap().name, "finger");
+        assert_eq!(graph.get_node(&Handle::from(7)).unwrap().name, "pinky");
+        assert!(graph.get_node(&Handle::from("root.body.arm.hand.glove")).is_some());
+        assert_eq!(graph.descendants(&Handle::from(5)).count(), 3);
+
+        // Removing from the prototype removes from every instance.
+        graph.edit_prototype(arm, |prototype| prototype.remove(&Handle::from("glove")));
+        assert_eq!(graph.descendants(&Handle::from(2)).count(), 2);
+        assert_eq!(graph.descendants(&Handle::from(5)).count(), 2);
+        assert!(graph.instances.iter().all(|instance| instance.nodes[3] == u32::MAX));
+
+        // Moving in the prototype moves in every instance.
+        graph.edit_prototype(arm, |prototype| {
+            let root = prototype.root.clone();
+            prototype.reparent(&Handle::from("finger"), &root)
+        });
+        assert_eq!(graph.get_node(&Handle::from(4)).unwrap().parent, 2);
+        assert_eq!(graph.get_node(&Handle::from(7)).unwrap().parent, 5);
+        assert!(graph.get_node(&Handle::from(6)).unwrap().children.is_empty());
+
+        assert!(graph.revert_override(&Handle::from(3)));
+        assert_eq!(components(&graph, 3), vec![ComponentRef::new(MESH, 2)]);
+        assert!(!graph.is_overridden(&Handle::from(3)));
+    }
+
+    #[test]
+    fn unpack() {
+        let (mut graph, arm) = snowman();
+        assert!(graph.unpack(&Handle::from(5)));
+        assert!(!graph.unpack(&Handle::from(5)));
+        graph.edit_prototype(arm, |prototype| prototype.push_child("elbow"));
+        assert_eq!(graph.descendants(&Handle::from(2)).count(), 3);
+        assert_eq!(graph.descendants(&Handle::from(5)).count(), 2);
+        assert!(graph.instance_of(&Handle::from(6)).is_none());
+
+        // Removed instances stop following their prototype.
+        graph.remove(&Handle::from(2));
+        graph.edit_prototype(arm, |prototype| prototype.push_child("wrist"));
+        assert!(graph.instances.is_empty());
+    }
+
+    #[test]
+    fn unpack_from_any_node() {
+        let (mut graph, arm) = snowman();
+        assert!(graph.unpack(&Handle::from(4)));
+        assert!(graph.instance_of(&Handle::from(2)).is_none());
+        assert!(!graph.unpack(&Handle::from(3)));
+        assert!(!graph.unpack(&Handle::from("body")));
+
+        // The other instance still follows, also after a round trip.
+        let mut graph: MachGraph = serde_json::from_str(&serde_json::to_string(&graph).unwrap()).unwrap();
+        graph.edit_prototype(arm, |prototype| prototype.push_child("elbow"));
+        let elbow = graph.nodes.len() as u32 - 1;
+        assert_eq!(graph.instance_of(&Handle::from(elbow)).map(|(_, root)| root.index), Some(Some(5)));
+        assert!(graph.unpack(&Handle::from(elbow)));
+        assert!(graph.instances.is_empty());
+    }
+
+    #[test]
+    fn prototype_from() {
+        let mut graph = MachGraph::default();
+        let button = graph.push_child("button");
+        graph.push_component(&button, (MESH, 3));
+        graph.push_child_of("hole", &button);
+        let body = graph.push_child("body");
+
+        let id = graph.prototype_from(&button).unwrap();
+        assert_eq!(graph.prototype(id).unwrap().nodes.len(), 2);
+        let copy = graph.instantiate(id, &body).unwrap();
+        assert_eq!(Handle::path(&graph, copy.index.unwrap()).unwrap(), "root.body.button");
+        assert_eq!(graph.components_of_type(&copy, MESH).collect::<Vec<u32>>(), vec![3]);
+        assert!(graph.get_node(&Handle::from("root.body.button.hole")).is_some());
+        assert!(graph.prototype_from(&Handle::from("missing")).is_none());
+        assert!(graph.instantiate(7, &body).is_none());
+    }
+
+    #[test]
+    fn serialize() {
+        let (graph, arm) = snowman();
+        let json = serde_json::to_string(&graph).unwrap();
+        let mut back: MachGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.instances.len(), 2);
+        back.edit_prototype(arm, |prototype| prototype.push_child("elbow"));
+        assert_eq!(back.descendants(&Handle::from(5)).count(), 3);
+        assert!(!serde_json::to_string(&MachGraph::default()).unwrap().contains("prototypes"));
+    }
+}
\ No newline at end of file
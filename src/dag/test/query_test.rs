@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod query {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    #[derive(Debug, PartialEq)]
+    struct Transform(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Mesh(&'static str);
+
+    struct Hidden;
+
+    /// Every node gets a Transform (its index), buttons, eyes and the hat get a Mesh,
+    /// and the middle button is hidden.
+    fn dressed() -> MachGraph {
+        let mut graph = snowman();
+        for index in (0..graph.nodes.len() as u32).rev() {
+            graph.store.insert(index, Transform(index as i32));
+        }
+        for (index, mesh) in [(12, "hat"), (10, "eye"), (11, "eye"), (6, "button"), (7, "button"), (8, "button")] {
+            graph.store.insert(index, Mesh(mesh));
+        }
+        graph.store.insert(7, Hidden);
+        graph
+    }
+
+    #[test]
+    fn with_and_without() {
+        let graph = dressed();
+        let query = graph.query::<(&Transform, &Mesh)>().without::<Hidden>();
+        let mut found: Vec<(String, i32, &str)> = query.iter()
+            .map(|(node, (transform, mesh))| (node.name.clone(), transform.0, mesh.0))
+            .collect();
+        found.sort_by_key(|(_, index, _)| *index);
+        assert_eq!(found, vec![
+            ("bottom_button".into(), 6, "button"),
+            ("top_button".into(), 8, "button"),
+            ("left".into(), 10, "eye"),
+            ("right".into(), 11, "eye"),
+            ("hat".into(), 12, "hat"),
+        ]);
+
+        assert_eq!(graph.query::<&Transform>().with::<Hidden>().iter().count(), 1);
+        assert_eq!(graph.query::<&Mesh>().iter().count(), 6);
+        assert_eq!(graph.query::<&Hidden>().without::<Mesh>().iter().count(), 0);
+        assert!(graph.query::<(&Transform, &Mesh)>().get(7).is_some());
+        assert!(graph.query::<(&Transform, &Mesh)>().get(5).is_none());
+    }
+
+    #[test]
+    fn subtree_pre_order() {
+        let mut graph = dressed();
+        let matches = |query: &Query<'_, (&Transform, Option<&Mesh>)>| -> Vec<(u32, bool)> {
+            query.iter().map(|(node, (_, mesh))| (node.index, mesh.is_some())).collect()
+        };
+
+        let query = graph.query::<(&Transform, Option<&Mesh>)>().subtree(&Handle::from("mid"));
+        assert_eq!(matches(&query), vec![(5, false), (6, true), (7, true), (8, true)]);
+
+        let order: Vec<u32> = graph.query::<&Transform>().pre_order().iter().map(|(node, _)| node.index).collect();
+        assert_eq!(order, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(graph.query::<&Transform>().subtree(&Handle::from("missing")).iter().count(), 0);
+
+        // Nothing is stored for removed nodes; queries skip them.
+        graph.remove(&Handle::from("top"));
+        assert_eq!(graph.query::<&Mesh>().iter().count(), 4);
+        assert_eq!(graph.query::<Option<&Mesh>>().iter().count(), 13);
+    }
+}
\ No newline at end of file
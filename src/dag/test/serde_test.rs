@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod serde_test {
     use crate::dag::*;
 
@@ -27,7 +28,36 @@ mod serde_test {
             });
         });
 
-        //let json = serde_json::to_string(&graph).expect("Error parsing graph to JSON.");
-        //assert_eq!(json, String::from("{\"name\":\"default\",\"index\":0,\"root\":{\"path\":\"root\",\"index\":0},\"nodes\":[{\"name\":\"root\",\"parent\":0,\"index\":0,\"children\":[1,12,13],\"components\":[]},{\"name\":\"body\",\"parent\":0,\"index\":1,\"children\":[2,5,9],\"components\":[]},{\"name\":\"base\",\"parent\":1,\"index\":2,\"children\":[3,4],\"components\":[]},{\"name\":\"left\",\"parent\":2,\"index\":3,\"children\":[],\"components\":[]},{\"name\":\"right\",\"parent\":2,\"index\":4,\"children\":[],\"components\":[]},{\"name\":\"mid\",\"parent\":1,\"index\":5,\"children\":[6,7,8],\"components\":[]},{\"name\":\"bottom_button\",\"parent\":5,\"index\":6,\"children\":[],\"components\":[]},{\"name\":\"middle_button\",\"parent\":5,\"index\":7,\"children\":[],\"components\":[]},{\"name\":\"top_button\",\"parent\":5,\"index\":8,\"children\":[],\"components\":[]},{\"name\":\"top\",\"parent\":1,\"index\":9,\"children\":[10,11],\"components\":[]},{\"name\":\"left\",\"parent\":9,\"index\":10,\"children\":[],\"components\":[]},{\"name\":\"right\",\"parent\":9,\"index\":11,\"children\":[],\"components\":[]},{\"name\":\"hat\",\"parent\":0,\"index\":12,\"children\":[],\"components\":[]},{\"name\":\"arms\",\"parent\":0,\"index\":13,\"children\":[14,15],\"components\":[]},{\"name\":\"left\",\"parent\":13,\"index\":14,\"children\":[],\"components\":[]},{\"name\":\"right\",\"parent\":13,\"index\":15,\"children\":[],\"components\":[]}]}"));
+        let json = graph.to_json().expect("Error writing graph to JSON.");
+        assert_eq!(json, String::from("{\"version\":1,\"name\":\"default\",\"index\":0,\"root\":0,\"nodes\":[{\"name\":\"root\",\"children\":[1,12,13]},{\"name\":\"body\",\"children\":[2,5,9]},{\"name\":\"base\",\"children\":[3,4]},{\"name\":\"left\"},{\"name\":\"right\"},{\"name\":\"mid\",\"children\":[6,7,8]},{\"name\":\"bottom_button\"},{\"name\":\"middle_button\"},{\"name\":\"top_button\"},{\"name\":\"top\",\"children\":[10,11]},{\"name\":\"left\"},{\"name\":\"right\"},{\"name\":\"hat\"},{\"name\":\"arms\",\"children\":[14,15]},{\"name\":\"left\"},{\"name\":\"right\"}]}"));
+        let back = MachGraph::<()>::from_json(&json).expect("Error reading graph from JSON.");
+        assert_eq!(back.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn component_refs() {
+        let mut graph = MachGraph::default();
+        let lamp = graph.push_child("lamp");
+        graph.push_component(&lamp, 4);
+        graph.push_component(&lamp, (2, 9));
+
+        let json = serde_json::to_string(&graph.get_node(&lamp).unwrap().components).unwrap();
+        assert_eq!(json, "[4,{\"kind\":2,\"index\":9}]");
+
+        let json = serde_json::to_string(&graph).unwrap();
+        let back: MachGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.nodes[1].components, vec![ComponentRef::from(4), ComponentRef::new(2, 9)]);
+    }
+
+    #[test]
+    fn untyped_components_file() {
+        let json = "{\"name\":\"old\",\"index\":0,\"root\":{\"path\":\"root\",\"index\":0},\"nodes\":[\
+            {\"name\":\"root\",\"parent\":0,\"index\":0,\"children\":[1],\"components\":[]},\
+            {\"name\":\"lamp\",\"parent\":0,\"index\":1,\"children\":[],\"components\":[3,5]}]}";
+        let graph: MachGraph = serde_json::from_str(json).unwrap();
+        let lamp = graph.get_node(&Handle::from("lamp")).unwrap();
+        assert!(!lamp.components[0].is_typed());
+        assert_eq!(lamp.components_of_type(ComponentRef::UNTYPED).collect::<Vec<u32>>(), vec![3, 5]);
+        assert_eq!(serde_json::to_string(&lamp.components).unwrap(), "[3,5]");
     }
 }
\ No newline at end of file
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod store {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32, f32);
+
+    #[derive(Debug, PartialEq)]
+    struct Label(&'static str);
+
+    #[test]
+    fn storage() {
+        let mut store = ComponentStore::new();
+        assert!(store.insert(3, Position(1.0, 2.0)).is_none());
+        assert!(store.insert(7, Position(3.0, 4.0)).is_none());
+        assert!(store.insert(5, Position(5.0, 6.0)).is_none());
+        assert!(store.insert(7, Label("seven")).is_none());
+        assert_eq!(store.type_count(), 2);
+
+        assert_eq!(store.insert(3, Position(0.0, 0.0)), Some(Position(1.0, 2.0)));
+        assert_eq!(store.get::<Position>(3), Some(&Position(0.0, 0.0)));
+        assert!(store.get::<Label>(3).is_none());
+        assert!(store.contains::<Label>(7));
+
+        // Removing keeps the storage dense and the moved value reachable.
+        assert_eq!(store.remove::<Position>(3), Some(Position(0.0, 0.0)));
+        assert_eq!(store.storage::<Position>().unwrap().len(), 2);
+        assert_eq!(store.get::<Position>(5), Some(&Position(5.0, 6.0)));
+        assert_eq!(store.get::<Position>(7), Some(&Position(3.0, 4.0)));
+        assert!(store.remove::<Position>(3).is_none());
+
+        for (_, position) in store.iter_mut::<Position>() {
+            position.0 += 10.0;
+        }
+        let mut xs: Vec<(u32, f32)> = store.iter::<Position>().map(|(index, position)| (index, position.0)).collect();
+        xs.sort_by_key(|(index, _)| *index);
+        assert_eq!(xs, vec![(5, 15.0), (7, 13.0)]);
+
+        assert_eq!(store.release(7), 2);
+        assert_eq!(store.release(7), 0);
+        assert_eq!(store.iter::<Label>().count(), 0);
+        assert!(format!("{:?}", store).contains("Position"));
+    }
+
+    #[test]
+    fn graph_components() {
+        let mut graph = snowman();
+        let hat = Handle::from("hat");
+        assert!(graph.insert_component(&hat, Label("top hat")).unwrap().is_none());
+        assert!(graph.insert_component(&Handle::from("mid"), Position(0.0, 1.0)).is_ok());
+        assert!(graph.insert_component(&Handle::from("top_button"), Position(0.0, 1.5)).is_ok());
+        assert_eq!(graph.insert_component(&Handle::from("scarf"), Label("red")), Err(Label("red")));
+
+        assert_eq!(graph.get_component::<Label>(&hat), Some(&Label("top hat")));
+        graph.get_component_mut::<Position>(&Handle::from("mid")).unwrap().1 = 2.0;
+        assert_eq!(graph.get_component::<Position>(&Handle::from(5)), Some(&Position(0.0, 2.0)));
+        assert_eq!(graph.take_component::<Label>(&hat), Some(Label("top hat")));
+        assert!(graph.get_component::<Label>(&hat).is_none());
+
+        // Removing a node releases the components of its subtree.
+        assert!(graph.remove(&Handle::from("mid")));
+        assert_eq!(graph.store.iter::<Position>().count(), 0);
+        assert!(graph.get_component::<Position>(&Handle::from(5)).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tags {
+    use std::collections::BTreeSet;
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Buttons and the hat are selectable, the body is static, the hat and middle button are dirty.
+    fn tagged() -> MachGraph {
+        let mut graph = snowman();
+        for name in ["bottom_button", "middle_button", "top_button", "hat"] {
+            assert!(graph.tag(&Handle::from(name), "selectable"));
+        }
+        for index in [1, 2, 5, 9] {
+            graph.tag(&Handle::from(index), "static");
+        }
+        graph.tag(&Handle::from("hat"), "dirty");
+        graph.tag(&Handle::from("middle_button"), "dirty");
+        graph
+    }
+
+    fn set(indices: &[u32]) -> BTreeSet<u32> {
+        indices.iter().copied().collect()
+    }
+
+    #[test]
+    fn tag_and_untag() {
+        let mut graph = tagged();
+        assert!(!graph.tag(&Handle::from("hat"), "dirty"));
+        assert!(!graph.tag(&Handle::from("missing"), "dirty"));
+        assert!(graph.has_tag(&Handle::from("hat"), "selectable"));
+        assert!(!graph.has_tag(&Handle::from("arms"), "selectable"));
+
+        let names: Vec<&str> = graph.tagged("selectable").map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["bottom_button", "middle_button", "top_button", "hat"]);
+        assert_eq!(graph.tags.count("static"), 4);
+        assert_eq!(graph.tags.of(12).collect::<Vec<&str>>(), vec!["dirty", "selectable"]);
+        assert_eq!(graph.tags.names().collect::<Vec<&str>>(), vec!["dirty", "selectable", "static"]);
+
+        assert!(graph.untag(&Handle::from("hat"), "dirty"));
+        assert!(!graph.untag(&Handle::from("hat"), "dirty"));
+        assert_eq!(graph.tags.clear_tag("dirty"), set(&[7]));
+        assert_eq!(graph.tags.names().count(), 2);
+        assert_eq!(graph.tagged("dirty").count(), 0);
+    }
+
+    #[test]
+    fn set_operations() {
+        let graph = tagged();
+        assert_eq!(graph.tags.all(&["selectable", "dirty"]), set(&[7, 12]));
+        assert_eq!(graph.tags.all(&["selectable", "static"]), set(&[]));
+        assert_eq!(graph.tags.all(&["selectable", "missing"]), set(&[]));
+        assert_eq!(graph.tags.any(&["dirty", "static"]), set(&[1, 2, 5, 7, 9, 12]));
+        assert_eq!(graph.tags.except("selectable", &["dirty"]), set(&[6, 8]));
+        assert!(graph.tags.all(&[]).is_empty());
+    }
+
+    #[test]
+    fn node_removal() {
+        let mut graph = tagged();
+        graph.remove(&Handle::from("mid"));
+        assert_eq!(graph.tags.all(&["selectable", "dirty"]), set(&[12]));
+        assert_eq!(graph.tags.nodes("static").collect::<Vec<u32>>(), vec![1, 2, 9]);
+        graph.remove(&Handle::from("hat"));
+        assert_eq!(graph.tags.names().collect::<Vec<&str>>(), vec!["static"]);
+    }
+
+    #[test]
+    fn serialize() {
+        let graph = tagged();
+        let json = serde_json::to_string(&graph).unwrap();
+        assert!(json.contains("\"tags\":{\"dirty\":[7,12],\"selectable\":[6,7,8,12],\"static\":[1,2,5,9]}"));
+        let back: MachGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.tags, graph.tags);
+        assert!(!serde_json::to_string(&snowman()).unwrap().contains("tags"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod topology {
+    use crate::dag::*;
+
+    fn indices(handles: &[Handle]) -> Vec<u32> {
+        handles.iter().map(|handle| handle.index.unwrap()).collect()
+    }
+
+    fn position(order: &[Handle], index: u32) -> usize {
+        order.iter().position(|handle| handle.index == Some(index)).expect("missing node")
+    }
+
+    #[test]
+    fn tree_order() {
+        let mut graph = MachGraph::default();
+        let left = graph.push_child("left");
+        graph.push_child_of("left_a", &left);
+        graph.push_child("right");
+
+        let order = graph.topological_order().expect("tree has no cycles");
+        assert_eq!(indices(&order), vec![0, 1, 3, 2]);
+        assert_eq!(order[0].path, "root");
+        assert!(graph.detect_cycles().is_empty());
+        assert!(!graph.has_cycles());
+    }
+
+    #[test]
+    fn shared_children() {
+        let mut graph = MachGraph::default();
+        let a = graph.push_child("a");
+        let b = graph.push_child("b");
+        let shared = graph.push_child_of("shared", &a);
+        let leaf = graph.push_child_of("leaf", &shared);
+        graph.get_node_mut(&b).unwrap().children.push(shared.index.unwrap());
+        graph.get_node_mut(&b).unwrap().children.push(leaf.index.unwrap());
+
+        let order = graph.topological_order().expect("no cycles");
+        assert_eq!(order.len(), 5);
+        for node in &graph.nodes {
+            for child in &node.children {
+                assert!(position(&order, node.index) < position(&order, *child));
+            }
+        }
+    }
+
+    #[test]
+    fn cycles() {
+        let mut graph = MachGraph::default();
+        let a = graph.push_child("a");
+        let b = graph.push_child_of("b", &a);
+        let c = graph.push_child_of("c", &b);
+        let d = graph.push_child("d");
+        graph.get_node_mut(&c).unwrap().children.push(a.index.unwrap());
+        graph.get_node_mut(&d).unwrap().children.push(d.index.unwrap());
+
+        let cycles = graph.detect_cycles();
+        assert_eq!(cycles.len(), 2);
+        let mut found: Vec<Vec<u32>> = cycles.iter().map(|cycle| indices(cycle)).collect();
+        found.sort();
+        assert_eq!(found, vec![vec![1, 2, 3], vec![4]]);
+        assert!(graph.has_cycles());
+
+        match graph.topological_order() {
+            Err(GraphError::Cycle(cycle)) => {
+                assert!(!cycle.is_empty());
+                let error = GraphError::Cycle(cycle).to_string();
+                assert!(error.starts_with("graph contains a cycle: "));
+            },
+            other => panic!("cycle not reported: {:?}", other),
+        }
+
+        graph.get_node_mut(&c).unwrap().children.clear();
+        graph.get_node_mut(&d).unwrap().children.clear();
+        assert!(graph.topological_order().is_ok());
+    }
+
+    /// root -> a -> b -> c, with c linking back to a.
+    fn cyclic() -> MachGraph {
+        let mut graph = MachGraph::default();
+        let a = graph.push_child("a");
+        let b = graph.push_child_of("b", &a);
+        let c = graph.push_child_of("c", &b);
+        graph.push_child("d");
+        graph.get_node_mut(&c).unwrap().children.push(a.index.unwrap());
+        graph
+    }
+
+    #[test]
+    fn traversal_guards() {
+        let mut graph = cyclic();
+
+        let names = Collect::names();
+        match graph.pre_visit(&names) {
+            Err(GraphError::Cycle(cycle)) => assert_eq!(indices(&cycle), vec![1, 2, 3]),
+            other => panic!("cycle not reported: {:?}", other),
+        }
+        assert_eq!(*names.items(), vec!["root", "a", "b", "c"]);
+
+        let names = Collect::names();
+        assert!(graph.post_visit_from(&Handle::from("b"), None, true, &names).is_err());
+        assert!(graph.pre_visit_from(&Handle::from("a"), Some(2), true, &names).is_ok());
+        assert!(graph.pre_visit_filtered(|_| true, |node| node.name != "c", &names).is_ok());
+        assert!(graph.fold_up(|_| 1, |_, size, child| size + child).is_err());
+        assert!(graph.propagate_down(0, |depth, _| Some(depth + 1)).is_err());
+        assert!(graph.check_acyclic(&Handle::from("d")).is_ok());
+        assert!(graph.check_acyclic(&graph.root.clone()).is_err());
+
+        let mut counts = CountByDepth::default();
+        assert!(graph.post_visit_mut(&mut counts).is_err());
+        assert!(graph.pre_visit_graph(&GraphVisitorNoop {}).is_err());
+    }
+
+    struct GraphVisitorNoop {}
+    impl GraphVisitor for GraphVisitorNoop {}
+
+    struct RemoveVisitor {}
+    impl CommandVisitor for RemoveVisitor {
+        fn visit(&mut self, node: &MachNode, commands: &mut GraphCommands) {
+            if node.name == "d" { commands.remove(Handle::from(node.index)); }
+        }
+    }
+
+    #[test]
+    fn guarded_helpers() {
+        let mut graph = cyclic();
+        assert!(graph.pre_visit_deferred(&mut RemoveVisitor {}).is_err());
+        assert!(graph.get_node(&Handle::from("d")).is_some());
+
+        assert_eq!(graph.descendants(&Handle::from("a")).count(), 2);
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 5);
+        assert_eq!(graph.leaves().count(), 1);
+
+        graph.nodes[1].parent = 3;
+        assert!(Handle::path(&graph, 2).is_none());
+        graph.nodes[1].parent = 0;
+
+        // 'a' is still a child of the root: only 'b' and 'c' go, and the link back to 'a' with them.
+        assert!(graph.remove(&Handle::from("b")));
+        assert!(graph.get_node(&Handle::from("c")).is_none());
+        assert!(graph.get_node(&Handle::from("a")).unwrap().children.is_empty());
+        assert_eq!(graph.get_root().unwrap().children, vec![1, 4]);
+        let names = Collect::names();
+        graph.pre_visit(&names).unwrap();
+        assert_eq!(*names.items(), vec!["root", "a", "d"]);
+        assert!(graph.topological_order().is_ok());
+    }
+
+    #[test]
+    fn shared_children_are_not_cycles() {
+        let mut graph = MachGraph::default();
+        let a = graph.push_child("a");
+        let b = graph.push_child("b");
+        let shared = graph.push_child_of("shared", &a);
+        graph.get_node_mut(&b).unwrap().children.push(shared.index.unwrap());
+
+        let names = Collect::names();
+        graph.pre_visit(&names).expect("shared children are not a cycle");
+        assert_eq!(*names.items(), vec!["root", "a", "shared", "b"]);
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 4);
+
+        // Removing one parent keeps the shared child, under its other parent.
+        assert!(graph.remove(&a));
+        let names = Collect::names();
+        graph.pre_visit(&names).unwrap();
+        assert_eq!(*names.items(), vec!["root", "b", "shared"]);
+        let node = graph.get_node(&shared).unwrap();
+        assert!(!node.removed);
+        assert_eq!(node.parent, b.index.unwrap());
+        assert_eq!(graph.get_root().unwrap().children, vec![b.index.unwrap()]);
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 3);
+
+        assert!(graph.remove(&b));
+        assert!(graph.get_node(&shared).is_none());
+        assert_eq!(graph.subtree_size(&graph.root.clone()), 1);
+    }
+
+    #[test]
+    fn diamonds_are_walked_once() {
+        // 64 stacked diamonds: walking every path would take 2^64 steps.
+        let mut graph = MachGraph::default();
+        let mut bottom = graph.root.clone();
+        for _ in 0..64 {
+            let left = graph.push_child_of("left", &bottom);
+            let right = graph.push_child_of("right", &bottom);
+            bottom = graph.push_child_of("bottom", &left);
+            graph.get_node_mut(&right).unwrap().children.push(bottom.index.unwrap());
+        }
+
+        let names = Collect::names();
+        graph.pre_visit(&names).unwrap();
+        assert_eq!(names.items().len(), graph.nodes.len());
+        let heights = graph.fold_up(|_| 0, |_, height, child| height.max(child + 1)).unwrap();
+        assert_eq!(heights[0], Some(128));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod view {
+    use crate::dag::*;
+    use crate::dag::test::snowman;
+
+    /// Copies the parent's components into each node.
+    #[derive(Default)]
+    struct InheritVisitor {}
+    impl GraphVisitor for InheritVisitor {
+        fn visit_mut(&mut self, graph: &GraphView, node: &mut MachNode) {
+            if let Some(parent) = graph.get_parent(node) {
+                for component in &parent.components {
+                    if !node.components.contains(component) { node.components.push(*component); }
+                }
+            }
+        }
+    }
+
+    /// Records "parent/name (siblings, children)" for every node.
+    #[derive(Default)]
+    struct DescribeVisitor {
+        pub lines: std::cell::RefCell<Vec<String>>,
+    }
+    impl GraphVisitor for DescribeVisitor {
+        fn visit(&self, graph: &GraphView, node: &MachNode) {
+            let parent = graph.get_parent(node).map(|parent| parent.name.as_str()).unwrap_or("-");
+            let line = format!("{}/{} ({}, {})", parent, node.name, graph.siblings(node).count(), graph.children(node).count());
+            self.lines.borrow_mut().push(line);
+        }
+    }
+
+    /// Sums the component counts of the children into the node.
+    #[derive(Default)]
+    struct GatherVisitor {}
+    impl GraphVisitor for GatherVisitor {
+        fn visit_mut(&mut self, graph: &GraphView, node: &mut MachNode) {
+            let gathered: Vec<ComponentRef> = graph.children(node).flat_map(|child| child.components.clone()).collect();
+            node.components.extend(gathered);
+        }
+    }
+
+    #[test]
+    fn copy_from_parent() {
+        let mut graph = snowman();
+        graph.push_component(&Handle::from("body"), 7);
+        graph.push_component(&Handle::from("mid"), 8);
+
+        let mut visitor = InheritVisitor::default();
+        graph.pre_visit_graph_mut(&mut visitor).unwrap();
+
+        assert_eq!(graph.get_node(&Handle::from("root.body.top.left")).unwrap().components, vec![ComponentRef::from(7)]);
+        assert_eq!(graph.get_node(&Handle::from("middle_button")).unwrap().components, vec![ComponentRef::from(8), ComponentRef::from(7)]);
+        assert!(graph.get_node(&Handle::from("arms.left")).unwrap().components.is_empty());
+    }
+
+    #[test]
+    fn gather_from_children() {
+        let mut graph = snowman();
+        graph.push_component(&Handle::from("root.body.top.left"), 1);
+        graph.push_component(&Handle::from("root.body.top.right"), 2);
+        graph.push_component(&Handle::from("hat"), 3);
+
+        let mut visitor = GatherVisitor::default();
+        graph.post_visit_graph_mut(&mut visitor).unwrap();
+
+        assert_eq!(graph.get_node(&Handle::from("top")).unwrap().components, vec![ComponentRef::from(1), ComponentRef::from(2)]);
+        assert_eq!(graph.get_node(&Handle::from("body")).unwrap().components, vec![ComponentRef::from(1), ComponentRef::from(2)]);
+        assert_eq!(graph.get_root().unwrap().components, vec![ComponentRef::from(1), ComponentRef::from(2), ComponentRef::from(3)]);
+    }
+
+    #[test]
+    fn inspect_neighbours() {
+        let graph = snowman();
+        let visitor = DescribeVisitor::default();
+        graph.pre_visit_graph(&visitor).unwrap();
+        let lines = visitor.lines.borrow();
+        assert_eq!(lines.len(), 16);
+        assert_eq!(lines[0], "-/root (0, 3)");
+        assert_eq!(lines[1], "root/body (2, 3)");
+        assert_eq!(lines[6], "mid/bottom_button (2, 0)");
+
+        let visitor = DescribeVisitor::default();
+        graph.visit_all_graph(&visitor);
+        assert_eq!(visitor.lines.borrow()[13], "root/arms (2, 2)");
+    }
+
+    #[test]
+    fn view_access() {
+        let mut graph = snowman();
+        let mut nodes = std::mem::take(&mut graph.nodes);
+        let (view, node) = GraphView::split(&mut nodes, 5, 0);
+        assert_eq!(node.name, "mid");
+        assert_eq!(view.len(), 16);
+        assert!(view.get(5).is_none());
+        assert_eq!(view.get(4).unwrap().name, "right");
+        assert_eq!(view.get(6).unwrap().name, "bottom_button");
+        assert_eq!(view.get_root().unwrap().name, "root");
+        assert_eq!(view.get_node(&Handle::from(15)).unwrap().name, "right");
+        assert!(view.get(16).is_none());
+    }
+}
\ No newline at end of file
@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod visitor {
     use crate::dag::*;
+    use crate::dag::test::snowman;
 
+    #[allow(dead_code)]
     #[derive(Default)]
     struct TestLogVisitor {
     }
@@ -82,10 +84,10 @@ mod visitor {
 
         //let log_visitor = TestLogVisitor::default();
         //println!("------ PRE ------");
-        //graph.pre_visit(&log_visitor);
+        //graph.pre_visit(&log_visitor).unwrap();
 
         let mut visitor = TestVisitor::default();
-        graph.pre_visit_mut(&mut visitor);
+        graph.pre_visit_mut(&mut visitor).unwrap();
         assert_eq!(visitor.path.len(), 16);
     }
 
@@ -116,10 +118,134 @@ mod visitor {
 
         //let log_visitor = TestLogVisitor::default();
         //println!("------ POST ------");
-        //graph.post_visit(&log_visitor);
+        //graph.post_visit(&log_visitor).unwrap();
 
         let mut visitor = TestVisitor::default();
-        graph.post_visit_mut(&mut visitor);
+        graph.post_visit_mut(&mut visitor).unwrap();
         assert_eq!(visitor.path.len(), 16);
     }
+
+    #[test]
+    fn pre_visit_from() {
+        let mut graph = snowman();
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_from_mut(&Handle::from("body"), None, true, &mut visitor).unwrap();
+        assert_eq!(visitor.path.len(), 11);
+        assert_eq!(visitor.path[0], "body");
+        assert_eq!(visitor.path[1], "base");
+        assert_eq!(visitor.path[2], "left");
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_from_mut(&Handle::from("body"), Some(1), false, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["base", "mid", "top"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_from_mut(&Handle::from("root"), Some(0), true, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["root"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_from_mut(&Handle::from("root.body.mid"), Some(4), true, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["mid", "bottom_button", "middle_button", "top_button"]);
+    }
+
+    #[test]
+    fn post_visit_from() {
+        let mut graph = snowman();
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_from_mut(&Handle::from("arms"), None, true, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["left", "right", "arms"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_from_mut(&Handle::from("arms"), None, false, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["left", "right"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_from_mut(&Handle::from("root"), Some(1), true, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["body", "hat", "arms", "root"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_from_mut(&Handle::from("missing"), None, true, &mut visitor).unwrap();
+        assert!(visitor.path.is_empty());
+    }
+
+    #[test]
+    fn visit_order() {
+        let mut graph = snowman();
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_mut(&mut visitor).unwrap();
+        assert_eq!(visitor.path, vec![
+            "root", "body", "base", "left", "right", "mid", "bottom_button", "middle_button", "top_button",
+            "top", "left", "right", "hat", "arms", "left", "right",
+        ]);
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_mut(&mut visitor).unwrap();
+        assert_eq!(visitor.path, vec![
+            "left", "right", "base", "bottom_button", "middle_button", "top_button", "mid",
+            "left", "right", "top", "body", "hat", "left", "right", "arms", "root",
+        ]);
+    }
+
+    #[derive(Default)]
+    struct PruneVisitor {
+        pub path: Vec<String>,
+    }
+    impl Visitor for PruneVisitor {
+        fn visit_mut(&mut self, node: &mut MachNode) {
+            self.path.push(node.name.clone());
+            if node.name == "body" { node.children.truncate(1); }
+        }
+    }
+
+    #[test]
+    fn pre_visit_mut_sees_changes() {
+        let mut graph = snowman();
+        let mut visitor = PruneVisitor::default();
+        graph.pre_visit_mut(&mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["root", "body", "base", "left", "right", "hat", "arms", "left", "right"]);
+    }
+
+    #[test]
+    fn pre_visit_filtered() {
+        let mut graph = snowman();
+        graph.push_component(&Handle::from("mid"), 1);
+        graph.push_component(&Handle::from("middle_button"), 2);
+        graph.push_component(&Handle::from("arms.left"), 3);
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_filtered_mut(|node| node.has_components(), |_| true, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["mid", "middle_button", "left"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_filtered_mut(|_| true, |node| node.name != "body" && node.name != "arms", &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["root", "body", "hat", "arms"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.pre_visit_filtered_mut(|node| node.has_components(), |node| node.name != "mid", &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["mid", "left"]);
+
+        let names = Collect::names();
+        graph.pre_visit_filtered(|node| !node.has_children(), |node| node.name != "body", &names).unwrap();
+        assert_eq!(*names.items(), vec!["hat", "left", "right"]);
+    }
+
+    #[test]
+    fn post_visit_filtered() {
+        let mut graph = snowman();
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_filtered_mut(|node| node.has_children(), |node| node.name != "base", &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["base", "mid", "top", "body", "arms", "root"]);
+
+        let mut visitor = TestVisitor::default();
+        graph.post_visit_filtered_mut(|_| true, |node| node.index == 0, &mut visitor).unwrap();
+        assert_eq!(visitor.path, vec!["body", "hat", "arms", "root"]);
+
+        let names = Collect::names();
+        graph.post_visit_filtered(|node| node.name.starts_with('l'), |node| node.name != "top", &names).unwrap();
+        assert_eq!(*names.items(), vec!["left", "left"]);
+    }
 }
\ No newline at end of file
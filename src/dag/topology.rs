@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use super::{MachGraph, MachNode, Handle, GraphError};
+
+
+///
+/// Topology implementation for MachGraph.
+/// Treats every entry of a node's children as an edge, so a child listed by several nodes
+/// (a shared child) has several predecessors. Removed nodes are ignored.
+///
+impl<T> MachGraph<T> {
+    /// All nodes ordered so that every node comes after all of its predecessors.
+    /// Fails with the first cycle found when the graph isn't acyclic.
+    pub fn topological_order(&self) -> Result<Vec<Handle>, GraphError> {
+        let mut in_degree = vec![0usize; self.nodes.len()];
+        for node in self.live_nodes() {
+            for child in self.live_children(node) { in_degree[child as usize] += 1; }
+        }
+
+        let mut queue: VecDeque<u32> = self.live_nodes()
+            .filter(|node| in_degree[node.index as usize] == 0)
+            .map(|node| node.index)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(index) = queue.pop_front() {
+            let node = &self.nodes[index as usize];
+            order.push(Handle::from(node));
+            for child in self.live_children(node) {
+                in_degree[child as usize] -= 1;
+                if in_degree[child as usize] == 0 { queue.push_back(child); }
+            }
+        }
+
+        if order.len() < self.live_nodes().count() {
+            let cycle = self.detect_cycles().into_iter().next().unwrap_or_default();
+            return Err(GraphError::Cycle(cycle));
+        }
+        Ok(order)
+    }
+
+
+    /// Cycles in this graph, one per group of nodes that can all reach each other
+    /// (strongly connected component). Each cycle starts at its lowest index and
+    /// lists the nodes in edge order; the last node links back to the first.
+    pub fn detect_cycles(&self) -> Vec<Vec<Handle>> {
+        self.strongly_connected()
+            .into_iter()
+            .filter_map(|component| self.cycle_in(&component))
+            .map(|cycle| cycle.into_iter().map(|index| Handle::from(&self.nodes[index as usize])).collect())
+            .collect()
+    }
+
+
+    /// Does this graph contain a cycle?
+    pub fn has_cycles(&self) -> bool {
+        !self.detect_cycles().is_empty()
+    }
+
+
+    /// Strongly connected components (Tarjan, with an explicit call stack instead of recursion).
+    fn strongly_connected(&self) -> Vec<Vec<u32>> {
+        const UNSEEN: u32 = u32::MAX;
+        let count = self.nodes.len();
+        let mut order = vec![UNSEEN; count];
+        let mut low = vec![0u32; count];
+        let mut on_stack = vec![false; count];
+        let mut stack: Vec<u32> = Vec::new();
+        let mut components = Vec::new();
+        let mut next = 0u32;
+
+        for start in self.live_nodes().map(|node| node.index) {
+            if order[start as usize] != UNSEEN { continue; }
+            let mut calls: Vec<(u32, usize)> = vec![(start, 0)];
+            order[start as usize] = next;
+            low[start as usize] = next;
+            next += 1;
+            stack.push(start);
+            on_stack[start as usize] = true;
+
+            while let Some(&(index, position)) = calls.last() {
+                let u = index as usize;
+                if let Some(child) = self.nodes[u].children.get(position) {
+                    if let Some(call) = calls.last_mut() { call.1 += 1; }
+                    let c = *child as usize;
+                    if c >= count || self.nodes[c].removed { continue; }
+                    if order[c] == UNSEEN {
+                        order[c] = next;
+                        low[c] = next;
+                        next += 1;
+                        stack.push(*child);
+                        on_stack[c] = true;
+                        calls.push((*child, 0));
+                    } else if on_stack[c] {
+                        low[u] = low[u].min(order[c]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low[parent as usize] = low[parent as usize].min(low[u]);
+                }
+                if low[u] == order[u] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member as usize] = false;
+                        component.push(member);
+                        if member == index { break; }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+
+    /// A cycle through the lowest index of a strongly connected component, if it has one.
+    fn cycle_in(&self, component: &[u32]) -> Option<Vec<u32>> {
+        let start = *component.iter().min()?;
+        if component.len() == 1 && !self.nodes[start as usize].children.contains(&start) { return None; }
+
+        // Breadth first search inside the component for the shortest way back to 'start'.
+        let mut inside = vec![false; self.nodes.len()];
+        for index in component { inside[*index as usize] = true; }
+        let mut previous: Vec<Option<u32>> = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::from([start]);
+        while let Some(index) = queue.pop_front() {
+            for child in self.live_children(&self.nodes[index as usize]) {
+                if child == start {
+                    let mut cycle = vec![index];
+                    while let Some(before) = previous[*cycle.last()? as usize] { cycle.push(before); }
+                    cycle.reverse();
+                    return Some(cycle);
+                }
+                if inside[child as usize] && previous[child as usize].is_none() {
+                    previous[child as usize] = Some(index);
+                    queue.push_back(child);
+                }
+            }
+        }
+        None
+    }
+
+
+    /// Nodes that haven't been removed.
+    fn live_nodes(&self) -> impl Iterator<Item = &MachNode<T>> {
+        self.nodes.iter().filter(|node| !node.removed)
+    }
+
+
+    /// Children of a node that are in range and haven't been removed.
+    fn live_children<'a>(&'a self, node: &'a MachNode<T>) -> impl Iterator<Item = u32> + 'a {
+        node.children.iter()
+            .copied()
+            .filter(|child| self.nodes.get(*child as usize).is_some_and(|child| !child.removed))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+use super::{MachNode, Handle};
+
+
+///
+/// GraphView.
+/// Read-only view of a graph's nodes, handed to a GraphVisitor alongside the node being visited.
+/// In the mutable visits the node being visited is borrowed mutably, so it is not part of the view.
+///
+#[derive(Debug)]
+pub struct GraphView<'a, T = ()> {
+    /// Nodes before the visited node (all nodes when nothing is borrowed mutably).
+    head: &'a [MachNode<T>],
+
+    /// Nodes after the visited node.
+    tail: &'a [MachNode<T>],
+
+    /// Index of the node borrowed mutably, if any.
+    visited: Option<u32>,
+
+    /// Root index of the graph.
+    root: u32,
+}
+
+
+///
+/// Clone implementation (a view is a pair of slices, so it copies whatever the payload).
+/// 
+impl<T> Clone for GraphView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for GraphView<'_, T> {}
+
+
+///
+/// GraphView implementation.
+/// 
+impl<'a, T> GraphView<'a, T> {
+    /// View of all nodes.
+    pub(crate) fn new(nodes: &'a [MachNode<T>], root: u32) -> Self {
+        Self {
+            head: nodes,
+            tail: &[],
+            visited: None,
+            root,
+        }
+    }
+
+
+    /// Split nodes into a view of every other node and the node at 'index' (must be in range).
+    pub(crate) fn split(nodes: &'a mut [MachNode<T>], index: u32, root: u32) -> (Self, &'a mut MachNode<T>) {
+        let (head, rest) = nodes.split_at_mut(index as usize);
+        let (node, tail) = rest.split_first_mut().expect("Index out of range");
+        let view = Self {
+            head,
+            tail,
+            visited: Some(index),
+            root,
+        };
+        (view, node)
+    }
+
+
+    /// Number of nodes in the graph (including a node visited mutably).
+    pub fn len(&self) -> usize {
+        self.head.len() + self.tail.len() + self.visited.map_or(0, |_| 1)
+    }
+
+
+    /// Is the graph empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+
+    /// Get a node by index. The node being visited mutably is not available.
+    pub fn get(&self, index: u32) -> Option<&'a MachNode<T>> {
+        let node = match self.visited {
+            Some(visited) if index == visited => None,
+            Some(visited) if index > visited => self.tail.get((index - visited - 1) as usize),
+            _ => self.head.get(index as usize),
+        };
+        node.filter(|node| !node.removed)
+    }
+
+
+    /// Get a node from a handle with an index.
+    pub fn get_node(&self, handle: &Handle) -> Option<&'a MachNode<T>> {
+        self.get(handle.index?)
+    }
+
+
+    /// Get root node.
+    pub fn get_root(&self) -> Option<&'a MachNode<T>> {
+        self.get(self.root)
+    }
+
+
+    /// Get the parent of a node.
+    pub fn get_parent(&self, node: &MachNode<T>) -> Option<&'a MachNode<T>> {
+        if !node.has_parent() { return None; }
+        self.get(node.parent)
+    }
+
+
+    /// Children of a node.
+    pub fn children<'n>(&self, node: &'n MachNode<T>) -> impl Iterator<Item = &'a MachNode<T>> + 'n where 'a: 'n {
+        let view = *self;
+        node.children.iter().filter_map(move |child| view.get(*child))
+    }
+
+
+    /// Siblings of a node (node not included).
+    pub fn siblings(&self, node: &MachNode<T>) -> impl Iterator<Item = &'a MachNode<T>> {
+        let view = *self;
+        let index = node.index;
+        self.get_parent(node)
+            .map(|parent| parent.children.iter())
+            .unwrap_or_default()
+            .filter(move |child| **child != index)
+            .filter_map(move |child| view.get(*child))
+    }
+}
\ No newline at end of file
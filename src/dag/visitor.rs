@@ -1,13 +1,57 @@
-use crate::dag::MachNode;
+use crate::dag::{MachNode, GraphView, GraphCommands, Chain, Filter};
 
 
 ///
 /// Visitor trait.
+/// Generic over the node payload type 'T' (none by default).
 /// 
-pub trait Visitor {
+pub trait Visitor<T = ()> {
     /// Visit a node.
-    fn visit(&self, _node: &MachNode) { /* Abstract */ }
+    fn visit(&self, _node: &MachNode<T>) { /* Abstract */ }
 
     /// Visit mutable node.
-    fn visit_mut(&mut self, node: &mut MachNode) { self.visit(node); }
+    fn visit_mut(&mut self, node: &mut MachNode<T>) { self.visit(node); }
+
+    /// Run this visitor and then another on every node, in one pass.
+    fn chain<V: Visitor<T>>(self, second: V) -> Chain<Self, V> where Self: Sized {
+        Chain { first: self, second }
+    }
+
+    /// Only visit nodes matching a predicate.
+    fn filter<P: Fn(&MachNode<T>) -> bool>(self, predicate: P) -> Filter<Self, P> where Self: Sized {
+        Filter { inner: self, predicate }
+    }
+}
+
+
+///
+/// Visitor implementation for mutable references, so combinators can borrow visitors.
+/// 
+impl<T, V: Visitor<T> + ?Sized> Visitor<T> for &mut V {
+    fn visit(&self, node: &MachNode<T>) { (**self).visit(node); }
+
+    fn visit_mut(&mut self, node: &mut MachNode<T>) { (**self).visit_mut(node); }
+}
+
+
+///
+/// GraphVisitor trait.
+/// Like Visitor, but also receives a read-only view of the graph to inspect parents, siblings and children.
+/// 
+pub trait GraphVisitor<T = ()> {
+    /// Visit a node.
+    fn visit(&self, _graph: &GraphView<T>, _node: &MachNode<T>) { /* Abstract */ }
+
+    /// Visit mutable node. The node is not part of the view while it is borrowed mutably.
+    fn visit_mut(&mut self, graph: &GraphView<T>, node: &mut MachNode<T>) { self.visit(graph, node); }
+}
+
+
+///
+/// CommandVisitor trait.
+/// Visits nodes while recording structural edits, which are applied after the traversal.
+/// 
+pub trait CommandVisitor<T = ()> {
+    /// Visit a node.
+    fn visit(&mut self, node: &MachNode<T>, commands: &mut GraphCommands);
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use super::{MachNode, Handle, GraphError};
+
+
+///
+/// Walk event.
+/// A node is entered before any of its children and left after all of them.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Entering a node (pre-order position).
+    Enter(u32),
+
+    /// Leaving a node (post-order position).
+    Leave(u32),
+}
+
+
+///
+/// Walk frame.
+/// 
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    /// Index of the node.
+    index: u32,
+
+    /// Position of the next child to enter.
+    next: usize,
+
+    /// Depth relative to the start node.
+    depth: u32,
+}
+
+
+///
+/// Walk state of a node.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    /// Not entered yet.
+    Unseen,
+
+    /// Entered and not left yet (on the current path).
+    OnPath,
+
+    /// Entered and left.
+    Done,
+}
+
+
+///
+/// Walk.
+/// Depth-first traversal cursor that only stores indices. It does not borrow the graph,
+/// the nodes are passed to every step so the caller is free to mutate a node between steps.
+/// Children are read when they are needed, so changes made while entering a node are respected.
+/// Removed children are skipped. A child that links back to a node on the current path (a cycle) ends the walk, see 'finish'.
+/// Every node is entered once: a shared child is walked under the first parent that reaches it and skipped under the others.
+///
+#[derive(Debug)]
+pub(crate) struct Walk {
+    stack: Vec<Frame>,
+    visits: Vec<Visit>,
+    cycle: Option<Vec<u32>>,
+    start: u32,
+    max_depth: Option<u32>,
+    include_start: bool,
+    started: bool,
+}
+
+
+///
+/// Walk implementation.
+/// 
+impl Walk {
+    /// New walk starting at a node index.
+    /// Depth is relative to the start node (start is depth 0). A max depth of 'None' walks every level.
+    pub(crate) fn new(start: u32, max_depth: Option<u32>, include_start: bool) -> Self {
+        Self {
+            stack: Vec::new(),
+            visits: Vec::new(),
+            cycle: None,
+            start,
+            max_depth,
+            include_start,
+            started: false,
+        }
+    }
+
+
+    /// Next step of the walk.
+    pub(crate) fn next<T>(&mut self, nodes: &[MachNode<T>]) -> Option<Step> {
+        if !self.started {
+            self.started = true;
+            if (self.start as usize) < nodes.len() {
+                self.visits = vec![Visit::Unseen; nodes.len()];
+                self.visits[self.start as usize] = Visit::OnPath;
+                self.stack.push(Frame { index: self.start, next: 0, depth: 0 });
+                if self.include_start { return Some(Step::Enter(self.start)); }
+            }
+        }
+        loop {
+            let within_depth = self.within_depth();
+            let frame = self.stack.last_mut()?;
+            if within_depth {
+                if let Some(child) = nodes[frame.index as usize].children.get(frame.next) {
+                    let (child, depth) = (*child, frame.depth + 1);
+                    frame.next += 1;
+                    if nodes.get(child as usize).is_some_and(|child| !child.removed) {
+                        match self.visits[child as usize] {
+                            Visit::Done => continue,
+                            Visit::OnPath => {
+                                self.stop_at_cycle(child);
+                                return None;
+                            },
+                            Visit::Unseen => self.visits[child as usize] = Visit::OnPath,
+                        }
+                        self.stack.push(Frame { index: child, next: 0, depth });
+                        return Some(Step::Enter(child));
+                    }
+                    continue;
+                }
+            }
+            let frame = self.stack.pop()?;
+            self.visits[frame.index as usize] = Visit::Done;
+            if frame.depth > 0 || self.include_start {
+                return Some(Step::Leave(frame.index));
+            }
+        }
+    }
+
+
+    /// Parent of the node that was just entered, within this walk.
+    pub(crate) fn parent(&self) -> Option<u32> {
+        let len = self.stack.len();
+        if len < 2 { return None; }
+        Some(self.stack[len - 2].index)
+    }
+
+
+    /// Don't enter the children of the node that was just entered (it is still left as usual).
+    pub(crate) fn skip_children(&mut self) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.next = usize::MAX;
+        }
+    }
+
+
+    /// Result of the walk: the cycle that ended it, if any.
+    pub(crate) fn finish<T>(&self, nodes: &[MachNode<T>]) -> Result<(), GraphError> {
+        match &self.cycle {
+            Some(cycle) => Err(GraphError::Cycle(cycle.iter().map(|index| Handle::from(&nodes[*index as usize])).collect())),
+            None => Ok(()),
+        }
+    }
+
+
+    /// Record the cycle from 'child' (already on the path) down to the current node and end the walk.
+    fn stop_at_cycle(&mut self, child: u32) {
+        let from = self.stack.iter().position(|frame| frame.index == child).unwrap_or(0);
+        self.cycle = Some(self.stack[from..].iter().map(|frame| frame.index).collect());
+        self.stack.clear();
+    }
+
+
+    /// Can the current node's children be entered?
+    fn within_depth(&self) -> bool {
+        match (self.stack.last(), self.max_depth) {
+            (Some(frame), Some(max)) => frame.depth < max,
+            _ => true,
+        }
+    }
+}
\ No newline at end of file